@@ -0,0 +1,109 @@
+use super::SessionFilter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionState {
+    Active,
+    Inactive,
+    #[cfg(windows)]
+    Expired,
+}
+
+/// Снимок одной аудиосессии в момент опроса.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioSession {
+    pub state: SessionState,
+    /// Пиковый уровень 0.0..=1.0, если бэкенд умеет его измерять.
+    pub peak: Option<f32>,
+    pub process_id: Option<u32>,
+    pub process_name: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[cfg(test)]
+impl AudioSession {
+    pub fn active() -> Self {
+        Self::with_state(SessionState::Active)
+    }
+
+    pub fn inactive() -> Self {
+        Self::with_state(SessionState::Inactive)
+    }
+
+    pub fn with_state(state: SessionState) -> Self {
+        Self {
+            state,
+            peak: None,
+            process_id: None,
+            process_name: None,
+            display_name: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActivityPolicy {
+    pub peak_threshold: Option<f32>,
+    pub filter: SessionFilter,
+}
+
+impl ActivityPolicy {
+    pub fn is_session_active(&self, session: &AudioSession) -> bool {
+        if session.state != SessionState::Active || !self.filter.accepts(session) {
+            return false;
+        }
+        match (self.peak_threshold, session.peak) {
+            (Some(threshold), Some(peak)) => peak > threshold,
+            _ => true,
+        }
+    }
+
+    pub fn is_active(&self, sessions: &[AudioSession]) -> bool {
+        sessions.iter().any(|session| self.is_session_active(session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_peak(peak: f32) -> AudioSession {
+        AudioSession { peak: Some(peak), ..AudioSession::active() }
+    }
+
+    #[test]
+    fn only_active_sessions_count() {
+        let policy = ActivityPolicy::default();
+
+        assert!(policy.is_session_active(&AudioSession::active()));
+        assert!(!policy.is_session_active(&AudioSession::inactive()));
+    }
+
+    #[test]
+    fn peak_must_exceed_threshold_when_measured() {
+        let policy = ActivityPolicy { peak_threshold: Some(0.01), ..ActivityPolicy::default() };
+
+        assert!(!policy.is_session_active(&with_peak(0.0)));
+        assert!(!policy.is_session_active(&with_peak(0.01)));
+        assert!(policy.is_session_active(&with_peak(0.5)));
+        // Бэкенды без измерения уровня не должны считаться вечно тихими.
+        assert!(policy.is_session_active(&AudioSession::active()));
+    }
+
+    #[test]
+    fn peak_is_ignored_without_threshold() {
+        assert!(ActivityPolicy::default().is_session_active(&with_peak(0.0)));
+    }
+
+    #[test]
+    fn filtered_out_sessions_are_not_active() {
+        let policy = ActivityPolicy {
+            peak_threshold: None,
+            filter: SessionFilter::new(vec![String::from("discord*")], Vec::new()),
+        };
+        let discord = AudioSession { process_name: Some(String::from("Discord.exe")), ..AudioSession::active() };
+
+        assert!(!policy.is_session_active(&discord));
+        assert!(!policy.is_active(&[discord, AudioSession::inactive()]));
+        assert!(policy.is_active(&[AudioSession::inactive(), AudioSession::active()]));
+    }
+}
@@ -0,0 +1,110 @@
+use super::AudioSession;
+
+/// Фильтр сессий по имени процесса или отображаемому имени.
+/// Шаблоны поддерживают `*` и `?`, регистр не учитывается.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionFilter {
+    pub ignore: Vec<String>,
+    pub trigger: Vec<String>,
+}
+
+impl SessionFilter {
+    pub fn new(ignore: Vec<String>, trigger: Vec<String>) -> Self {
+        Self { ignore, trigger }
+    }
+
+    pub fn accepts(&self, session: &AudioSession) -> bool {
+        if self.ignore.iter().any(|pattern| matches_session(pattern, session)) {
+            return false;
+        }
+        self.trigger.is_empty()
+            || self.trigger.iter().any(|pattern| matches_session(pattern, session))
+    }
+}
+
+fn matches_session(pattern: &str, session: &AudioSession) -> bool {
+    [&session.process_name, &session.display_name]
+        .into_iter()
+        .flatten()
+        .any(|name| glob_match(pattern, name))
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(process_name: &str, display_name: Option<&str>) -> AudioSession {
+        AudioSession {
+            process_name: Some(process_name.to_string()),
+            display_name: display_name.map(String::from),
+            ..AudioSession::active()
+        }
+    }
+
+    #[test]
+    fn glob_supports_wildcards_and_ignores_case() {
+        assert!(glob_match("spotify.exe", "Spotify.EXE"));
+        assert!(glob_match("*.exe", "chrome.exe"));
+        assert!(glob_match("chrom?", "chrome"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXXbYYbZc"));
+        assert!(!glob_match("chrom?", "chromium"));
+        assert!(!glob_match("*.exe", "chrome.exe.bak"));
+        assert!(!glob_match("", "chrome"));
+    }
+
+    #[test]
+    fn empty_filter_accepts_everything() {
+        assert!(SessionFilter::default().accepts(&session("anything", None)));
+        assert!(SessionFilter::default().accepts(&AudioSession::active()));
+    }
+
+    #[test]
+    fn ignore_wins_over_trigger() {
+        let filter = SessionFilter::new(vec![String::from("teams*")], vec![String::from("*")]);
+
+        assert!(!filter.accepts(&session("Teams.exe", None)));
+        assert!(filter.accepts(&session("vlc", None)));
+    }
+
+    #[test]
+    fn trigger_limits_to_matching_sessions() {
+        let filter = SessionFilter::new(Vec::new(), vec![String::from("spotify*")]);
+
+        assert!(filter.accepts(&session("spotify", None)));
+        assert!(filter.accepts(&session("app.exe", Some("Spotify Premium"))));
+        assert!(!filter.accepts(&session("vlc", None)));
+        assert!(!filter.accepts(&AudioSession::active()));
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(windows)]
+mod wasapi;
+#[cfg(target_os = "linux")]
+mod pulse;
+mod activity;
+mod filter;
+#[cfg(test)]
+mod scripted;
+
+#[cfg(windows)]
+pub use wasapi::AudioMonitor;
+#[cfg(target_os = "linux")]
+pub use pulse::PulseAudioMonitor;
+pub use activity::{ActivityPolicy, AudioSession, SessionState};
+pub use filter::SessionFilter;
+#[cfg(test)]
+pub use scripted::ScriptedAudioSource;
+
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AudioError {
+    #[cfg(windows)]
+    #[error("Failed to get default audio endpoint")]
+    EndpointError,
+    #[cfg(windows)]
+    #[error("Failed to get session manager")]
+    SessionManagerError,
+    #[cfg(windows)]
+    #[error("Failed to get session enumerator")]
+    SessionEnumError,
+    #[error("Audio backend error: {0}")]
+    BackendError(String),
+    #[error("Audio backend is not available: {0}")]
+    BackendUnavailable(String),
+    #[cfg(windows)]
+    #[error("Windows API error: {0}")]
+    WindowsError(#[from] windows::core::Error),
+}
+
+impl AudioError {
+    /// Аудиослужба может перезапускаться или ещё не стартовать, а вот
+    /// отсутствующий бэкенд сам не появится.
+    pub fn is_transient(&self) -> bool {
+        !matches!(self, AudioError::BackendUnavailable(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Render,
+    Capture,
+}
+
+/// Источник информации о текущих аудиосессиях.
+pub trait AudioActivitySource: Send + Sync {
+    fn sessions(&self, flow: Flow) -> Result<Vec<AudioSession>, AudioError>;
+
+    fn is_audio_playing(&self, policy: &ActivityPolicy) -> Result<bool, AudioError> {
+        Ok(policy.is_active(&self.sessions(Flow::Render)?))
+    }
+
+    fn is_microphone_in_use(&self, policy: &ActivityPolicy) -> Result<bool, AudioError> {
+        Ok(policy.is_active(&self.sessions(Flow::Capture)?))
+    }
+}
+
+impl<T: AudioActivitySource + ?Sized> AudioActivitySource for Arc<T> {
+    fn sessions(&self, flow: Flow) -> Result<Vec<AudioSession>, AudioError> {
+        (**self).sessions(flow)
+    }
+}
+
+pub fn default_source() -> Box<dyn AudioActivitySource> {
+    #[cfg(windows)]
+    {
+        Box::new(AudioMonitor)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(PulseAudioMonitor::new())
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        log::warn!("No native audio backend on this platform, audio is always reported idle");
+        Box::new(NoAudio)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+struct NoAudio;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl AudioActivitySource for NoAudio {
+    fn sessions(&self, _flow: Flow) -> Result<Vec<AudioSession>, AudioError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,290 @@
+use std::process::Command;
+
+use super::{AudioActivitySource, AudioError, AudioSession, Flow, SessionState};
+
+/// Поток PulseAudio: sink input для воспроизведения или source output для записи.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PulseStream {
+    pub index: u32,
+    pub corked: bool,
+    pub application_name: Option<String>,
+    pub process_id: Option<u32>,
+    pub process_binary: Option<String>,
+}
+
+impl PulseStream {
+    fn to_session(&self) -> AudioSession {
+        AudioSession {
+            state: if self.corked { SessionState::Inactive } else { SessionState::Active },
+            peak: None,
+            process_id: self.process_id,
+            process_name: self.process_binary.clone(),
+            display_name: self.application_name.clone(),
+        }
+    }
+}
+
+/// Источник активности для PulseAudio и PipeWire (через pipewire-pulse),
+/// опрашивающий списки потоков с помощью `pactl`.
+#[derive(Default)]
+pub struct PulseAudioMonitor {
+    // Адрес сервера; без него pactl находит сервер сеанса сам.
+    server: Option<String>,
+}
+
+impl PulseAudioMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub fn with_server(server: String) -> Self {
+        Self { server: Some(server) }
+    }
+
+    pub fn streams(&self, flow: Flow) -> Result<Vec<PulseStream>, AudioError> {
+        let (list, header) = match flow {
+            Flow::Render => ("sink-inputs", "Sink Input #"),
+            Flow::Capture => ("source-outputs", "Source Output #"),
+        };
+
+        let mut command = Command::new("pactl");
+        if let Some(server) = &self.server {
+            command.arg(format!("--server={}", server));
+        }
+        let output = command
+            .env("LC_ALL", "C")
+            .args(["list", list])
+            .output()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AudioError::BackendUnavailable(String::from("pactl is not installed")),
+                _ => AudioError::BackendError(format!("Failed to run pactl: {}", e)),
+            })?;
+
+        if !output.status.success() {
+            return Err(AudioError::BackendError(format!(
+                "pactl exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(parse_streams(&String::from_utf8_lossy(&output.stdout), header))
+    }
+}
+
+impl AudioActivitySource for PulseAudioMonitor {
+    fn sessions(&self, flow: Flow) -> Result<Vec<AudioSession>, AudioError> {
+        Ok(self.streams(flow)?.iter().map(PulseStream::to_session).collect())
+    }
+}
+
+pub fn parse_streams(output: &str, header: &str) -> Vec<PulseStream> {
+    let mut streams = Vec::new();
+    let mut current: Option<PulseStream> = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(index) = line.strip_prefix(header) {
+            streams.extend(current.take());
+            current = Some(PulseStream {
+                index: index.trim().parse().unwrap_or_default(),
+                ..PulseStream::default()
+            });
+            continue;
+        }
+
+        let Some(stream) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(value) = line.strip_prefix("Corked:") {
+            stream.corked = value.trim() == "yes";
+        } else if let Some((key, value)) = line.split_once(" = ") {
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "application.name" => stream.application_name = Some(value),
+                "application.process.id" => stream.process_id = value.parse().ok(),
+                "application.process.binary" => stream.process_binary = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    streams.extend(current);
+    streams
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::process::{Child, Stdio};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::audio::ActivityPolicy;
+    use crate::testing::{daemon_unavailable, TempDir};
+
+    const SINK: &str = "btmnr_test";
+
+    const SINK_INPUTS: &str = r#"Sink Input #42
+	Driver: protocol-native.c
+	Owner Module: 10
+	Client: 57
+	Sink: 1
+	Sample Specification: s16le 2ch 44100Hz
+	Corked: no
+	Mute: no
+	Properties:
+		media.name = "Playback"
+		application.name = "Firefox"
+		application.process.id = "1234"
+		application.process.binary = "firefox"
+
+Sink Input #43
+	Driver: protocol-native.c
+	Corked: yes
+	Properties:
+		application.name = "Music Player"
+		application.process.id = "not-a-pid"
+"#;
+
+    #[test]
+    fn parses_each_stream_with_its_properties() {
+        let streams = parse_streams(SINK_INPUTS, "Sink Input #");
+
+        assert_eq!(
+            streams,
+            [
+                PulseStream {
+                    index: 42,
+                    corked: false,
+                    application_name: Some(String::from("Firefox")),
+                    process_id: Some(1234),
+                    process_binary: Some(String::from("firefox")),
+                },
+                PulseStream {
+                    index: 43,
+                    corked: true,
+                    application_name: Some(String::from("Music Player")),
+                    process_id: None,
+                    process_binary: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn corked_streams_become_inactive_sessions() {
+        let sessions: Vec<_> = parse_streams(SINK_INPUTS, "Sink Input #").iter().map(PulseStream::to_session).collect();
+
+        assert_eq!(sessions[0].state, SessionState::Active);
+        assert_eq!(sessions[0].process_name.as_deref(), Some("firefox"));
+        assert_eq!(sessions[1].state, SessionState::Inactive);
+    }
+
+    #[test]
+    fn ignores_other_headers_and_empty_output() {
+        assert!(parse_streams("", "Source Output #").is_empty());
+        assert!(parse_streams(SINK_INPUTS, "Source Output #").is_empty());
+    }
+
+    /// Отдельный pulseaudio с null sink, чтобы тест не зависел ни от
+    /// звуковой карты, ни от сервера текущего сеанса.
+    struct PrivatePulse {
+        daemon: Child,
+        server: String,
+        _dir: TempDir,
+    }
+
+    impl PrivatePulse {
+        fn start() -> Option<Self> {
+            let pulse = Self::spawn();
+            if pulse.is_none() {
+                daemon_unavailable("pulseaudio", "BTMNR_REQUIRE_PULSE");
+            }
+            pulse
+        }
+
+        fn spawn() -> Option<Self> {
+            // Без pactl и pacat тест всё равно нечем проводить.
+            for tool in ["pactl", "pacat"] {
+                Command::new(tool).arg("--version").stdout(Stdio::null()).status().ok()?;
+            }
+
+            let dir = TempDir::new("pulse");
+            let socket = dir.join("native");
+            let mut command = Command::new("pulseaudio");
+            command
+                .args(["-n", "--daemonize=no", "--exit-idle-time=-1", "--use-pid-file=no", "--disable-shm=yes"])
+                .arg(format!("--load=module-native-protocol-unix auth-anonymous=1 socket={}", socket.display()))
+                .arg(format!("--load=module-null-sink sink_name={}", SINK))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            for variable in ["HOME", "XDG_RUNTIME_DIR", "XDG_CONFIG_HOME", "PULSE_RUNTIME_PATH", "PULSE_STATE_PATH"] {
+                command.env(variable, dir.join(""));
+            }
+            let mut pulse = Self {
+                daemon: command.spawn().ok()?,
+                server: format!("unix:{}", socket.display()),
+                _dir: dir,
+            };
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !socket.exists() {
+                if pulse.daemon.try_wait().ok()?.is_some() || Instant::now() > deadline {
+                    return None;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Some(pulse)
+        }
+
+        fn play(&self) -> Child {
+            Command::new("pacat")
+                .args(["--playback", "--raw", "--client-name=btmnr-test"])
+                .arg(format!("--server={}", self.server))
+                .arg(format!("--device={}", SINK))
+                .stdin(File::open("/dev/zero").unwrap())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap()
+        }
+    }
+
+    impl Drop for PrivatePulse {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn wait_for(monitor: &PulseAudioMonitor, expected: bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while monitor.is_audio_playing(&ActivityPolicy::default()).unwrap() != expected {
+            assert!(Instant::now() < deadline, "playback was not reported as {}", expected);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn reports_playback_into_a_null_sink() {
+        let Some(pulse) = PrivatePulse::start() else { return };
+        let monitor = PulseAudioMonitor::with_server(pulse.server.clone());
+        assert!(monitor.streams(Flow::Render).unwrap().is_empty());
+
+        let mut player = pulse.play();
+        wait_for(&monitor, true);
+        let streams = monitor.streams(Flow::Render).unwrap();
+        assert_eq!(streams.len(), 1);
+        assert!(!streams[0].corked);
+        assert_eq!(streams[0].application_name.as_deref(), Some("btmnr-test"));
+        assert_eq!(streams[0].process_id, Some(player.id()));
+        assert!(monitor.streams(Flow::Capture).unwrap().is_empty());
+
+        player.kill().unwrap();
+        player.wait().unwrap();
+        wait_for(&monitor, false);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::{AudioActivitySource, AudioError, AudioSession, Flow};
+
+/// Источник активности, возвращающий заранее заданную последовательность
+/// снимков сессий. Когда сценарий закончился, повторяется последний снимок.
+/// Воспроизведение и запись описываются независимыми сценариями.
+pub struct ScriptedAudioSource {
+    render: Script,
+    capture: Script,
+}
+
+#[derive(Default)]
+struct Script {
+    steps: Mutex<VecDeque<Result<Vec<AudioSession>, AudioError>>>,
+    last: Mutex<Vec<AudioSession>>,
+}
+
+impl Script {
+    fn push(&self, step: Result<Vec<AudioSession>, AudioError>) {
+        self.steps.lock().unwrap().push_back(step);
+    }
+
+    fn next(&self) -> Result<Vec<AudioSession>, AudioError> {
+        let mut last = self.last.lock().unwrap();
+        match self.steps.lock().unwrap().pop_front() {
+            Some(Ok(sessions)) => {
+                *last = sessions.clone();
+                Ok(sessions)
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(last.clone()),
+        }
+    }
+}
+
+fn sessions_for(playing: bool) -> Vec<AudioSession> {
+    if playing { vec![AudioSession::active()] } else { Vec::new() }
+}
+
+impl ScriptedAudioSource {
+    pub fn new<I: IntoIterator<Item = bool>>(steps: I) -> Self {
+        let source = Self::idle();
+        for playing in steps {
+            source.push(playing);
+        }
+        source
+    }
+
+    pub fn idle() -> Self {
+        Self {
+            render: Script::default(),
+            capture: Script::default(),
+        }
+    }
+
+    pub fn push(&self, playing: bool) {
+        self.push_sessions(Flow::Render, sessions_for(playing));
+    }
+
+    pub fn push_microphone(&self, in_use: bool) {
+        self.push_sessions(Flow::Capture, sessions_for(in_use));
+    }
+
+    pub fn push_sessions(&self, flow: Flow, sessions: Vec<AudioSession>) {
+        self.script(flow).push(Ok(sessions));
+    }
+
+    pub fn push_error(&self, flow: Flow, error: AudioError) {
+        self.script(flow).push(Err(error));
+    }
+
+    fn script(&self, flow: Flow) -> &Script {
+        match flow {
+            Flow::Render => &self.render,
+            Flow::Capture => &self.capture,
+        }
+    }
+}
+
+impl AudioActivitySource for ScriptedAudioSource {
+    fn sessions(&self, flow: Flow) -> Result<Vec<AudioSession>, AudioError> {
+        self.script(flow).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::{AudioActivitySource, ActivityPolicy, AudioError, AudioSession, Flow, ScriptedAudioSource, SessionState};
+
+    #[test]
+    fn replays_steps_and_then_repeats_the_last_snapshot() {
+        let source = ScriptedAudioSource::new([true, false, true]);
+        let policy = ActivityPolicy::default();
+
+        let playing: Vec<bool> = (0..5).map(|_| source.is_audio_playing(&policy).unwrap()).collect();
+        assert_eq!(playing, [true, false, true, true, true]);
+    }
+
+    #[test]
+    fn render_and_capture_scripts_are_independent() {
+        let source = ScriptedAudioSource::idle();
+        source.push_microphone(true);
+        let policy = ActivityPolicy::default();
+
+        assert!(!source.is_audio_playing(&policy).unwrap());
+        assert!(source.is_microphone_in_use(&policy).unwrap());
+    }
+
+    #[test]
+    fn errors_do_not_replace_the_last_snapshot() {
+        let source = ScriptedAudioSource::idle();
+        source.push(true);
+        source.push_error(Flow::Render, AudioError::BackendError(String::from("gone")));
+        let policy = ActivityPolicy::default();
+
+        assert!(source.is_audio_playing(&policy).unwrap());
+        assert!(source.is_audio_playing(&policy).is_err());
+        assert!(source.is_audio_playing(&policy).unwrap());
+    }
+
+    #[test]
+    fn pushed_sessions_are_returned_as_is() {
+        let source = ScriptedAudioSource::idle();
+        let sessions = vec![AudioSession::inactive(), AudioSession::with_state(SessionState::Active)];
+        source.push_sessions(Flow::Capture, sessions.clone());
+
+        assert_eq!(source.sessions(Flow::Capture).unwrap(), sessions);
+        assert!(source.sessions(Flow::Render).unwrap().is_empty());
+    }
+}
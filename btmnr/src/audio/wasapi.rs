@@ -0,0 +1,125 @@
+use windows::Win32::Media::Audio::{
+    IAudioSessionManager2, IAudioSessionEnumerator,
+    IAudioSessionControl, IAudioSessionControl2, IMMDevice, IMMDeviceEnumerator,
+    MMDeviceEnumerator, eRender, eCapture, eConsole,
+    AudioSessionState, AudioSessionStateActive, AudioSessionStateExpired,
+};
+use windows::Win32::Media::Audio::Endpoints::IAudioMeterInformation;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_ALL};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::core::{ComInterface, PWSTR};
+
+use super::{AudioActivitySource, AudioError, AudioSession, Flow, SessionState};
+
+pub struct AudioMonitor;
+
+impl AudioActivitySource for AudioMonitor {
+    fn sessions(&self, flow: Flow) -> Result<Vec<AudioSession>, AudioError> {
+        let data_flow = match flow {
+            Flow::Render => eRender,
+            Flow::Capture => eCapture,
+        };
+
+        unsafe {
+            let enumerator: IMMDeviceEnumerator = CoCreateInstance(
+                &MMDeviceEnumerator,
+                None,
+                CLSCTX_ALL
+            ).map_err(|e| AudioError::WindowsError(e))?;
+
+            let device: IMMDevice = enumerator
+                .GetDefaultAudioEndpoint(data_flow, eConsole)
+                .map_err(|_| AudioError::EndpointError)?;
+
+            let session_manager: IAudioSessionManager2 = device
+                .cast::<IAudioSessionManager2>()
+                .map_err(|_| AudioError::SessionManagerError)?;
+
+            let session_enum: IAudioSessionEnumerator = session_manager
+                .GetSessionEnumerator()
+                .map_err(|_| AudioError::SessionEnumError)?;
+
+            let count = session_enum.GetCount()
+                .map_err(|e| AudioError::WindowsError(e))?;
+
+            let mut sessions = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                if let Ok(session) = session_enum.GetSession(i) {
+                    sessions.push(snapshot(&session));
+                }
+            }
+            Ok(sessions)
+        }
+    }
+}
+
+unsafe fn snapshot(session: &IAudioSessionControl) -> AudioSession {
+    let state = session.GetState()
+        .map(map_state)
+        .unwrap_or(SessionState::Inactive);
+
+    let peak = session
+        .cast::<IAudioMeterInformation>()
+        .and_then(|meter| meter.GetPeakValue())
+        .ok();
+
+    let process_id = session
+        .cast::<IAudioSessionControl2>()
+        .and_then(|session2| session2.GetProcessId())
+        .ok()
+        .filter(|&pid| pid != 0);
+
+    let display_name = session
+        .GetDisplayName()
+        .ok()
+        .and_then(|name| take_string(name))
+        .filter(|name| !name.is_empty());
+
+    AudioSession {
+        state,
+        peak,
+        process_id,
+        process_name: process_id.and_then(|pid| process_name(pid)),
+        display_name,
+    }
+}
+
+unsafe fn take_string(value: PWSTR) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let result = value.to_string().ok();
+    CoTaskMemFree(Some(value.0 as *const _));
+    result
+}
+
+unsafe fn process_name(pid: u32) -> Option<String> {
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+    let mut buffer = [0u16; 260];
+    let mut size = buffer.len() as u32;
+    let result = QueryFullProcessImageNameW(
+        handle,
+        PROCESS_NAME_WIN32,
+        PWSTR(buffer.as_mut_ptr()),
+        &mut size,
+    );
+    let _ = CloseHandle(handle);
+    result.ok()?;
+
+    let path = String::from_utf16_lossy(&buffer[..size as usize]);
+    path.rsplit('\\').next().map(String::from)
+}
+
+fn map_state(state: AudioSessionState) -> SessionState {
+    if state == AudioSessionStateActive {
+        SessionState::Active
+    } else if state == AudioSessionStateExpired {
+        SessionState::Expired
+    } else {
+        SessionState::Inactive
+    }
+}
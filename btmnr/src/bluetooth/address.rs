@@ -0,0 +1,142 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// MAC-адрес Bluetooth-устройства. Байты хранятся в порядке записи:
+/// `AA:BB:CC:DD:EE:FF` — это `[0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BluetoothAddress([u8; 6]);
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AddressParseError {
+    #[error("expected 6 octets like AA:BB:CC:DD:EE:FF, found {0}")]
+    OctetCount(usize),
+    #[error("octet {position} ({octet:?}) is not a two-digit hexadecimal number")]
+    InvalidOctet { position: usize, octet: String },
+    #[error("mixes ':' and '-' separators")]
+    MixedSeparators,
+}
+
+impl BluetoothAddress {
+    /// Байты в порядке `BLUETOOTH_ADDRESS::rgBytes`: младший байт первым.
+    #[cfg(windows)]
+    pub fn to_le_bytes(self) -> [u8; 6] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
+
+    #[cfg(windows)]
+    pub fn from_le_bytes(mut bytes: [u8; 6]) -> Self {
+        bytes.reverse();
+        Self(bytes)
+    }
+}
+
+/// Числовая форма, в которой адрес хранят Win32 (`ullLong`) и ядро Linux.
+impl From<BluetoothAddress> for u64 {
+    fn from(address: BluetoothAddress) -> Self {
+        address.0.iter().fold(0, |value, &byte| (value << 8) | u64::from(byte))
+    }
+}
+
+/// Старшие 16 бит отбрасываются: адрес занимает 48 бит.
+impl From<u64> for BluetoothAddress {
+    fn from(value: u64) -> Self {
+        let bytes = value.to_be_bytes();
+        Self([bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+    }
+}
+
+impl FromStr for BluetoothAddress {
+    type Err = AddressParseError;
+
+    /// Принимает шестнадцатеричные байты в любом регистре, разделённые
+    /// `:` или `-`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = match (s.contains(':'), s.contains('-')) {
+            (true, true) => return Err(AddressParseError::MixedSeparators),
+            (false, true) => '-',
+            _ => ':',
+        };
+        let octets: Vec<&str> = s.split(separator).collect();
+        if octets.len() != 6 {
+            return Err(AddressParseError::OctetCount(octets.len()));
+        }
+
+        let mut bytes = [0u8; 6];
+        for (position, (byte, octet)) in bytes.iter_mut().zip(&octets).enumerate() {
+            // from_str_radix принимает знак, поэтому цифры проверяются отдельно.
+            if octet.len() != 2 || !octet.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(AddressParseError::InvalidOctet { position: position + 1, octet: octet.to_string() });
+            }
+            *byte = u8::from_str_radix(octet, 16).expect("validated hex octet");
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for BluetoothAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}", a, b, c, d, e, g)
+    }
+}
+
+impl fmt::Debug for BluetoothAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BluetoothAddress({})", self)
+    }
+}
+
+impl Serialize for BluetoothAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BluetoothAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .map_err(|e| de::Error::custom(format!("invalid Bluetooth address {:?}: {}", text, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_either_separator_and_case_and_displays_uppercase() {
+        let colon: BluetoothAddress = "0a:1b:2c:3d:4e:5f".parse().unwrap();
+        let dash: BluetoothAddress = "0A-1B-2C-3D-4E-5F".parse().unwrap();
+        assert_eq!(colon, dash);
+        assert_eq!(colon.to_string(), "0A:1B:2C:3D:4E:5F");
+        assert_eq!(colon.to_string().parse::<BluetoothAddress>(), Ok(colon));
+    }
+
+    #[test]
+    fn malformed_addresses_are_rejected() {
+        assert_eq!("00:11:22:33:44".parse::<BluetoothAddress>(), Err(AddressParseError::OctetCount(5)));
+        assert_eq!("".parse::<BluetoothAddress>(), Err(AddressParseError::OctetCount(1)));
+        assert_eq!(
+            "00:11:22:33:44:G5".parse::<BluetoothAddress>(),
+            Err(AddressParseError::InvalidOctet { position: 6, octet: String::from("G5") })
+        );
+        assert_eq!(
+            "00:+1:22:33:44:55".parse::<BluetoothAddress>(),
+            Err(AddressParseError::InvalidOctet { position: 2, octet: String::from("+1") })
+        );
+        assert_eq!("00:11-22:33:44:55".parse::<BluetoothAddress>(), Err(AddressParseError::MixedSeparators));
+    }
+
+    #[test]
+    fn numeric_form_round_trips() {
+        let address: BluetoothAddress = "AA:BB:CC:DD:EE:FF".parse().unwrap();
+        assert_eq!(u64::from(address), 0xAABB_CCDD_EEFF);
+        assert_eq!(BluetoothAddress::from(0xAABB_CCDD_EEFF_u64), address);
+        assert_eq!(BluetoothAddress::from(0xFFFF_AABB_CCDD_EEFF_u64), address);
+    }
+}
@@ -0,0 +1,460 @@
+use async_trait::async_trait;
+use log::{error, info};
+use tokio::sync::OnceCell;
+use zbus::{fdo::{self, ObjectManagerProxy}, proxy, zvariant::{OwnedObjectPath, OwnedValue}, Connection, DBusError};
+use std::collections::HashMap;
+
+use super::{
+    BluetoothAddress, BluetoothError, DBusMethodError, DeviceInfo, HeadsetLink, LinkState, PlatformError, Profile,
+    ProfileReport,
+};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+trait Device1 {
+    fn connect(&self) -> zbus::Result<()>;
+    fn disconnect(&self) -> zbus::Result<()>;
+    fn connect_profile(&self, uuid: &str) -> zbus::Result<()>;
+    fn disconnect_profile(&self, uuid: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn connected(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn paired(&self) -> zbus::Result<bool>;
+}
+
+pub struct BluezController {
+    device_address: BluetoothAddress,
+    connection: OnceCell<Connection>,
+}
+
+impl BluezController {
+    pub fn new(device_address: BluetoothAddress) -> Self {
+        Self {
+            device_address,
+            connection: OnceCell::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_connection(connection: Connection, device_address: BluetoothAddress) -> Self {
+        Self {
+            device_address,
+            connection: OnceCell::new_with(Some(connection)),
+        }
+    }
+
+    async fn connection(&self) -> Result<&Connection, BluetoothError> {
+        self.connection
+            .get_or_try_init(Connection::system)
+            .await
+            .map_err(|e| {
+                error!("Failed to connect to the system bus: {}", e);
+                BluetoothError::DBusError(e)
+            })
+    }
+
+    async fn find_device_object(&self) -> Result<(OwnedObjectPath, DeviceInfo), BluetoothError> {
+        let connection = self.connection().await?;
+        let manager = ObjectManagerProxy::builder(connection)
+            .destination(BLUEZ_SERVICE)?
+            .path("/")?
+            .build()
+            .await?;
+
+        let objects = manager.get_managed_objects().await.map_err(|e| {
+            error!("Failed to enumerate BlueZ objects: {}", e);
+            BluetoothError::EnumerationError { source: fdo_error(&e) }
+        })?;
+
+        for (path, interfaces) in objects {
+            let Some(properties) = interfaces
+                .iter()
+                .find(|(name, _)| name.as_str() == DEVICE_INTERFACE)
+                .map(|(_, properties)| properties)
+            else {
+                continue;
+            };
+
+            // BlueZ отдаёт адрес строкой; сравниваются разобранные байты.
+            let Ok(address) = string_property(properties, "Address").parse::<BluetoothAddress>() else {
+                continue;
+            };
+            if address == self.device_address {
+                let device = DeviceInfo {
+                    address,
+                    name: string_property(properties, "Name"),
+                    connected: bool_property(properties, "Connected"),
+                    authenticated: bool_property(properties, "Paired"),
+                    remembered: bool_property(properties, "Trusted"),
+                };
+                return Ok((path, device));
+            }
+        }
+
+        Err(BluetoothError::DeviceNotFound { address: self.device_address })
+    }
+
+    async fn device_proxy(&self) -> Result<Device1Proxy<'_>, BluetoothError> {
+        let (path, _) = self.find_device_object().await?;
+        let proxy = Device1Proxy::builder(self.connection().await?)
+            .destination(BLUEZ_SERVICE)?
+            .path(path)?
+            .build()
+            .await?;
+        Ok(proxy)
+    }
+}
+
+fn string_property(properties: &HashMap<String, OwnedValue>, name: &str) -> String {
+    properties
+        .get(name)
+        .and_then(|value| value.downcast_ref::<&str>().ok())
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+fn bool_property(properties: &HashMap<String, OwnedValue>, name: &str) -> bool {
+    properties
+        .get(name)
+        .and_then(|value| value.downcast_ref::<bool>().ok())
+        .unwrap_or(false)
+}
+
+#[async_trait]
+impl HeadsetLink for BluezController {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+        let (_, device) = self.find_device_object().await?;
+        Ok(device)
+    }
+
+    async fn state(&self) -> Result<LinkState, BluetoothError> {
+        let device = self.device_proxy().await?;
+        Ok(if device.connected().await? { LinkState::Connected } else { LinkState::Disconnected })
+    }
+
+    async fn set_profile(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError> {
+        let device = self.device_proxy().await?;
+        set_profile(&device, self.device_address, profile, enabled).await
+    }
+
+    async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        let device = self.device_proxy().await?;
+
+        info!("Found target device, checking pairing");
+        if !device.paired().await? {
+            error!("Device {} is not paired", self.device_address);
+            return Err(BluetoothError::AuthenticationError {
+                address: self.device_address,
+                source: None,
+            });
+        }
+
+        let mut report = ProfileReport::default();
+        for &profile in profiles {
+            info!("Connecting {} profile", profile);
+            report.record(profile, set_profile(&device, self.device_address, profile, true).await);
+        }
+
+        let report = report.into_result()?;
+        info!("Successfully connected to device {}", self.device_address);
+        Ok(report)
+    }
+
+    async fn disconnect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        let device = self.device_proxy().await?;
+
+        let mut report = ProfileReport::default();
+        for &profile in profiles {
+            info!("Disconnecting {} profile", profile);
+            report.record(profile, set_profile(&device, self.device_address, profile, false).await);
+        }
+
+        // Отключение профилей не всегда разрывает само соединение, а при
+        // ошибке профиля устройство тем более остаётся занятым, поэтому
+        // отпускаем его полностью до того, как сообщать об ошибках профилей.
+        if device.connected().await? {
+            device.disconnect().await?;
+        }
+        let report = report.into_result()?;
+
+        info!("Successfully disconnected from device {}", self.device_address);
+        Ok(report)
+    }
+}
+
+async fn set_profile(
+    device: &Device1Proxy<'_>,
+    address: BluetoothAddress,
+    profile: Profile,
+    enabled: bool,
+) -> Result<(), BluetoothError> {
+    let result = if enabled {
+        device.connect_profile(&profile.uuid()).await
+    } else {
+        device.disconnect_profile(&profile.uuid()).await
+    };
+    result.map_err(|e| {
+        error!("Failed to {} {} profile: {}", if enabled { "connect" } else { "disconnect" }, profile, e);
+        BluetoothError::ServiceStateError {
+            address,
+            profile,
+            source: method_error(&e),
+        }
+    })
+}
+
+/// Имя ошибки D-Bus из ответа на вызов метода. Остальные ошибки zbus
+/// (обрыв соединения, ошибки разбора) имени не имеют.
+pub fn method_error(error: &zbus::Error) -> Option<PlatformError> {
+    match error {
+        zbus::Error::MethodError(name, message, _) => {
+            Some(DBusMethodError::new(name.as_str(), message.clone().unwrap_or_default()).into())
+        }
+        zbus::Error::FDO(error) => fdo_error(error),
+        _ => None,
+    }
+}
+
+fn fdo_error(error: &fdo::Error) -> Option<PlatformError> {
+    match error {
+        fdo::Error::ZBus(error) => method_error(error),
+        error => Some(DBusMethodError::new(error.name().as_str(), error.description().unwrap_or_default()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use zbus::{fdo::ObjectManager, interface, DBusError};
+
+    use super::*;
+    use crate::bluetooth::ErrorCode;
+    use crate::testing::daemon_unavailable;
+
+    const DEVICE_PATH: &str = "/org/bluez/hci0/dev_00_11_22_33_44_55";
+
+    /// Отдельный dbus-daemon на время теста, чтобы не трогать системную шину.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Option<Self> {
+            let bus = Self::spawn();
+            if bus.is_none() {
+                daemon_unavailable("dbus-daemon", "BTMNR_REQUIRE_DBUS");
+            }
+            bus
+        }
+
+        fn spawn() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+            Some(Self { daemon, address: address.trim().to_string() })
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    #[derive(DBusError, Debug)]
+    #[zbus(prefix = "org.bluez.Error")]
+    enum MockError {
+        #[zbus(error)]
+        ZBus(zbus::Error),
+        NotReady(String),
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        connected: bool,
+        paired: bool,
+        failing: bool,
+        calls: Vec<String>,
+    }
+
+    struct MockDevice(Arc<Mutex<MockState>>);
+
+    impl MockDevice {
+        fn record(&self, call: String) -> Result<(), MockError> {
+            let mut state = self.0.lock().unwrap();
+            state.calls.push(call);
+            if state.failing {
+                Err(MockError::NotReady(String::from("Resource Not Ready")))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[interface(name = "org.bluez.Device1")]
+    impl MockDevice {
+        fn connect(&self) -> Result<(), MockError> {
+            self.record(String::from("Connect"))
+        }
+
+        fn disconnect(&self) -> Result<(), MockError> {
+            self.0.lock().unwrap().connected = false;
+            self.0.lock().unwrap().calls.push(String::from("Disconnect"));
+            Ok(())
+        }
+
+        fn connect_profile(&self, uuid: &str) -> Result<(), MockError> {
+            self.record(format!("ConnectProfile {}", uuid))
+        }
+
+        fn disconnect_profile(&self, uuid: &str) -> Result<(), MockError> {
+            self.record(format!("DisconnectProfile {}", uuid))
+        }
+
+        #[zbus(property)]
+        fn address(&self) -> String {
+            String::from("00:11:22:33:44:55")
+        }
+
+        #[zbus(property)]
+        fn name(&self) -> String {
+            String::from("Mock headset")
+        }
+
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            self.0.lock().unwrap().connected
+        }
+
+        #[zbus(property)]
+        fn paired(&self) -> bool {
+            self.0.lock().unwrap().paired
+        }
+
+        #[zbus(property)]
+        fn trusted(&self) -> bool {
+            true
+        }
+    }
+
+    struct MockBluez {
+        state: Arc<Mutex<MockState>>,
+        // Соединения держат имя org.bluez и объекты, пока живёт тест.
+        _server: Connection,
+        client: Connection,
+        _bus: PrivateBus,
+    }
+
+    impl MockBluez {
+        async fn start(state: MockState) -> Option<Self> {
+            let bus = PrivateBus::start()?;
+            let state = Arc::new(Mutex::new(state));
+            let server = zbus::connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .serve_at("/", ObjectManager)
+                .unwrap()
+                .serve_at(DEVICE_PATH, MockDevice(state.clone()))
+                .unwrap()
+                .name(BLUEZ_SERVICE)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let client = zbus::connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            Some(Self { state, _server: server, client, _bus: bus })
+        }
+
+        fn controller(&self, address: &str) -> BluezController {
+            BluezController::with_connection(self.client.clone(), address.parse().unwrap())
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.state.lock().unwrap().calls.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_device_by_address_bytes() {
+        let Some(bluez) = MockBluez::start(MockState::default()).await else { return };
+
+        let device = bluez.controller("00-11-22-33-44-55").find_device().await.unwrap();
+        assert_eq!(device.name, "Mock headset");
+        assert!(device.remembered);
+
+        let missing = bluez.controller("00:11:22:33:44:66").find_device().await;
+        assert!(matches!(missing, Err(BluetoothError::DeviceNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn connect_requires_pairing() {
+        let Some(bluez) = MockBluez::start(MockState::default()).await else { return };
+
+        let result = bluez.controller("00:11:22:33:44:55").connect(&[Profile::A2dpSink]).await;
+        assert!(matches!(result, Err(BluetoothError::AuthenticationError { .. })));
+        assert!(bluez.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn connect_enables_each_profile_by_uuid() {
+        let state = MockState { paired: true, ..MockState::default() };
+        let Some(bluez) = MockBluez::start(state).await else { return };
+
+        let report = bluez
+            .controller("00:11:22:33:44:55")
+            .connect(&[Profile::A2dpSink, Profile::HandsFree])
+            .await
+            .unwrap();
+        assert_eq!(report.succeeded, [Profile::A2dpSink, Profile::HandsFree]);
+        assert_eq!(
+            bluez.calls(),
+            [
+                format!("ConnectProfile {}", Profile::A2dpSink.uuid()),
+                format!("ConnectProfile {}", Profile::HandsFree.uuid()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_bluez_is_reported_as_radio_unavailable() {
+        let Some(bus) = PrivateBus::start() else { return };
+        let client = zbus::connection::Builder::address(bus.address.as_str()).unwrap().build().await.unwrap();
+        let controller = BluezController::with_connection(client, "00:11:22:33:44:55".parse().unwrap());
+
+        let error = controller.find_device().await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::RadioUnavailable);
+    }
+
+    #[tokio::test]
+    async fn disconnect_releases_device_even_when_profiles_fail() {
+        let state = MockState { connected: true, paired: true, failing: true, ..MockState::default() };
+        let Some(bluez) = MockBluez::start(state).await else { return };
+
+        let error = bluez.controller("00:11:22:33:44:55").disconnect(&[Profile::A2dpSink]).await.unwrap_err();
+        let BluetoothError::ServiceStateError { source: Some(PlatformError::DBus(source)), .. } = &error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert_eq!(source.name, "org.bluez.Error.NotReady");
+        assert_eq!(error.code(), ErrorCode::RadioUnavailable);
+        assert_eq!(
+            bluez.calls(),
+            [format!("DisconnectProfile {}", Profile::A2dpSink.uuid()), String::from("Disconnect")]
+        );
+        assert!(!bluez.state.lock().unwrap().connected);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+use thiserror::Error;
+
+/// Исходная ошибка платформы, по которой `BluetoothError` выбирает
+/// код точнее, чем по одному своему варианту.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum PlatformError {
+    #[cfg(any(windows, test))]
+    #[error(transparent)]
+    Win32(#[from] Win32Error),
+    #[cfg(any(target_os = "linux", test))]
+    #[error(transparent)]
+    DBus(#[from] DBusMethodError),
+}
+
+impl PlatformError {
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            #[cfg(any(windows, test))]
+            PlatformError::Win32(e) => code_for_win32(e.code),
+            #[cfg(any(target_os = "linux", test))]
+            PlatformError::DBus(e) => code_for_dbus(&e.name),
+        }
+    }
+}
+
+/// Ошибка Win32, сохранённая без зависимости от крейта `windows`,
+/// чтобы таблица кодов проверялась тестами на любой платформе.
+#[cfg(any(windows, test))]
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("{message} (Win32 error {code})")]
+pub struct Win32Error {
+    pub code: u32,
+    pub message: String,
+}
+
+#[cfg(any(windows, test))]
+impl Win32Error {
+    pub fn new(code: u32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    /// HRESULT вида 0x8007XXXX несёт в младших битах код Win32;
+    /// остальные HRESULT сохраняются как есть.
+    pub fn from_hresult(hresult: i32, message: impl Into<String>) -> Self {
+        let hresult = hresult as u32;
+        let code = if hresult & 0xFFFF_0000 == 0x8007_0000 { hresult & 0xFFFF } else { hresult };
+        Self::new(code, message)
+    }
+}
+
+#[cfg(windows)]
+impl From<&windows::core::Error> for Win32Error {
+    fn from(error: &windows::core::Error) -> Self {
+        Self::from_hresult(error.code().0, error.message().to_string())
+    }
+}
+
+/// Ответ с ошибкой на вызов метода D-Bus. BlueZ передаёт причину именем
+/// ошибки (`org.bluez.Error.NotReady`), а текст годится только для логов.
+#[cfg(any(target_os = "linux", test))]
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("{message} ({name})")]
+pub struct DBusMethodError {
+    pub name: String,
+    pub message: String,
+}
+
+#[cfg(any(target_os = "linux", test))]
+impl DBusMethodError {
+    pub fn new(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { name: name.into(), message: message.into() }
+    }
+}
+
+/// Стабильный код причины сбоя для логов и статуса. Строковые значения
+/// не должны меняться: на них могут опираться внешние скрипты.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    DeviceNotFound,
+    DeviceNotConnected,
+    // BlueZ не сообщает об этом отдельной ошибкой.
+    #[cfg(any(windows, test))]
+    NotPaired,
+    AuthenticationFailed,
+    AuthenticationCancelled,
+    AccessDenied,
+    Timeout,
+    Busy,
+    RadioUnavailable,
+    ServiceStateFailed,
+    EnumerationFailed,
+    Bus,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::DeviceNotFound => "device_not_found",
+            ErrorCode::DeviceNotConnected => "device_not_connected",
+            #[cfg(any(windows, test))]
+            ErrorCode::NotPaired => "not_paired",
+            ErrorCode::AuthenticationFailed => "authentication_failed",
+            ErrorCode::AuthenticationCancelled => "authentication_cancelled",
+            ErrorCode::AccessDenied => "access_denied",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Busy => "busy",
+            ErrorCode::RadioUnavailable => "radio_unavailable",
+            ErrorCode::ServiceStateFailed => "service_state_failed",
+            ErrorCode::EnumerationFailed => "enumeration_failed",
+            ErrorCode::Bus => "bus_error",
+        }
+    }
+
+    /// Повтор бессмыслен, пока пользователь сам не спарит устройство
+    /// или не выдаст права.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(any(windows, test))]
+            ErrorCode::NotPaired => false,
+            ErrorCode::AuthenticationFailed | ErrorCode::AuthenticationCancelled | ErrorCode::AccessDenied => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Коды Win32, которые возвращают функции BluetoothApis, и их смысл.
+#[cfg(any(windows, test))]
+const WIN32_CODES: &[(u32, ErrorCode)] = &[
+    (5, ErrorCode::AccessDenied), // ERROR_ACCESS_DENIED
+    (21, ErrorCode::RadioUnavailable), // ERROR_NOT_READY
+    (170, ErrorCode::Busy), // ERROR_BUSY
+    (258, ErrorCode::Timeout), // WAIT_TIMEOUT
+    (259, ErrorCode::DeviceNotFound), // ERROR_NO_MORE_ITEMS
+    (1060, ErrorCode::RadioUnavailable), // ERROR_SERVICE_DOES_NOT_EXIST
+    (1167, ErrorCode::DeviceNotConnected), // ERROR_DEVICE_NOT_CONNECTED
+    (1168, ErrorCode::DeviceNotFound), // ERROR_NOT_FOUND
+    (1223, ErrorCode::AuthenticationCancelled), // ERROR_CANCELLED
+    (1244, ErrorCode::NotPaired), // ERROR_NOT_AUTHENTICATED
+    (1460, ErrorCode::Timeout), // ERROR_TIMEOUT
+];
+
+#[cfg(any(windows, test))]
+pub fn code_for_win32(code: u32) -> Option<ErrorCode> {
+    WIN32_CODES
+        .iter()
+        .find(|(win32, _)| *win32 == code)
+        .map(|(_, code)| *code)
+}
+
+/// Имена ошибок BlueZ и шины D-Bus и их смысл.
+#[cfg(any(target_os = "linux", test))]
+const DBUS_CODES: &[(&str, ErrorCode)] = &[
+    ("org.bluez.Error.NotReady", ErrorCode::RadioUnavailable),
+    ("org.bluez.Error.InProgress", ErrorCode::Busy),
+    ("org.bluez.Error.NotConnected", ErrorCode::DeviceNotConnected),
+    ("org.bluez.Error.DoesNotExist", ErrorCode::DeviceNotFound),
+    ("org.bluez.Error.AuthenticationFailed", ErrorCode::AuthenticationFailed),
+    ("org.bluez.Error.AuthenticationRejected", ErrorCode::AuthenticationFailed),
+    ("org.bluez.Error.AuthenticationCanceled", ErrorCode::AuthenticationCancelled),
+    ("org.bluez.Error.AuthenticationTimeout", ErrorCode::Timeout),
+    ("org.bluez.Error.NotPermitted", ErrorCode::AccessDenied),
+    ("org.bluez.Error.NotAuthorized", ErrorCode::AccessDenied),
+    ("org.freedesktop.DBus.Error.AccessDenied", ErrorCode::AccessDenied),
+    ("org.freedesktop.DBus.Error.NoReply", ErrorCode::Timeout),
+    ("org.freedesktop.DBus.Error.ServiceUnknown", ErrorCode::RadioUnavailable),
+    ("org.freedesktop.DBus.Error.UnknownObject", ErrorCode::DeviceNotFound),
+];
+
+#[cfg(any(target_os = "linux", test))]
+pub fn code_for_dbus(name: &str) -> Option<ErrorCode> {
+    DBUS_CODES
+        .iter()
+        .find(|(dbus, _)| *dbus == name)
+        .map(|(_, code)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_known_win32_codes() {
+        assert_eq!(code_for_win32(5), Some(ErrorCode::AccessDenied));
+        assert_eq!(code_for_win32(1244), Some(ErrorCode::NotPaired));
+        assert_eq!(code_for_win32(1460), Some(ErrorCode::Timeout));
+        assert_eq!(code_for_win32(0), None);
+        assert_eq!(code_for_win32(0x8007_048F), None);
+    }
+
+    #[test]
+    fn unwraps_win32_codes_from_hresult() {
+        let error = Win32Error::from_hresult(0x8007_048F_u32 as i32, "The device is not connected.");
+        assert_eq!(error.code, 1167);
+        assert_eq!(PlatformError::from(error).code(), Some(ErrorCode::DeviceNotConnected));
+
+        // E_FAIL не несёт кода Win32 и сохраняется целиком.
+        assert_eq!(Win32Error::from_hresult(0x8000_4005_u32 as i32, "").code, 0x8000_4005);
+    }
+
+    #[test]
+    fn maps_dbus_error_names() {
+        let not_ready = PlatformError::from(DBusMethodError::new("org.bluez.Error.NotReady", "Resource Not Ready"));
+        assert_eq!(not_ready.code(), Some(ErrorCode::RadioUnavailable));
+        assert_eq!(code_for_dbus("org.bluez.Error.InProgress"), Some(ErrorCode::Busy));
+        assert_eq!(code_for_dbus("org.bluez.Error.Failed"), None);
+    }
+
+    #[test]
+    fn only_user_action_errors_are_permanent() {
+        assert!(ErrorCode::Busy.is_transient());
+        assert!(ErrorCode::RadioUnavailable.is_transient());
+        assert!(!ErrorCode::NotPaired.is_transient());
+        assert!(!ErrorCode::AccessDenied.is_transient());
+    }
+}
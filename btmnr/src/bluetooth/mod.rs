@@ -0,0 +1,190 @@
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod bluez;
+mod address;
+mod codes;
+mod profiles;
+#[cfg(test)]
+mod simulated;
+
+#[cfg(windows)]
+pub use win32::BluetoothController;
+#[cfg(target_os = "linux")]
+pub use bluez::BluezController;
+pub use address::{AddressParseError, BluetoothAddress};
+pub use codes::{ErrorCode, PlatformError};
+#[cfg(target_os = "linux")]
+pub use codes::DBusMethodError;
+#[cfg(windows)]
+pub use codes::Win32Error;
+pub use profiles::Profile;
+#[cfg(test)]
+pub use simulated::SimulatedHeadset;
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BluetoothError {
+    #[error("Device {address} not found")]
+    DeviceNotFound { address: BluetoothAddress },
+    #[error("Failed to authenticate device {address}{}", cause(.source))]
+    AuthenticationError { address: BluetoothAddress, source: Option<PlatformError> },
+    #[error("Failed to change {profile} service on device {address}{}", cause(.source))]
+    ServiceStateError { address: BluetoothAddress, profile: Profile, source: Option<PlatformError> },
+    #[error("Failed to enumerate devices{}", cause(.source))]
+    EnumerationError { source: Option<PlatformError> },
+    #[cfg(target_os = "linux")]
+    #[error("D-Bus error: {0}")]
+    DBusError(#[from] zbus::Error),
+}
+
+fn cause(source: &Option<PlatformError>) -> String {
+    source.as_ref().map(|e| format!(": {}", e)).unwrap_or_default()
+}
+
+impl BluetoothError {
+    /// Исходная ошибка платформы определяет код точнее, чем сам вариант.
+    pub fn code(&self) -> ErrorCode {
+        let (source, fallback) = match self {
+            BluetoothError::DeviceNotFound { .. } => (None, ErrorCode::DeviceNotFound),
+            BluetoothError::AuthenticationError { source, .. } => (source.as_ref(), ErrorCode::AuthenticationFailed),
+            BluetoothError::ServiceStateError { source, .. } => (source.as_ref(), ErrorCode::ServiceStateFailed),
+            BluetoothError::EnumerationError { source } => (source.as_ref(), ErrorCode::EnumerationFailed),
+            #[cfg(target_os = "linux")]
+            BluetoothError::DBusError(e) => {
+                return bluez::method_error(e).and_then(|e| e.code()).unwrap_or(ErrorCode::Bus);
+            }
+        };
+        source.and_then(PlatformError::code).unwrap_or(fallback)
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.code().is_transient()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkState {
+    Connected,
+    Disconnected,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub address: BluetoothAddress,
+    pub name: String,
+    pub connected: bool,
+    pub authenticated: bool,
+    pub remembered: bool,
+}
+
+/// Результат включения или выключения набора профилей.
+#[derive(Debug, Default)]
+pub struct ProfileReport {
+    pub succeeded: Vec<Profile>,
+    pub failed: Vec<(Profile, BluetoothError)>,
+}
+
+impl ProfileReport {
+    pub fn record(&mut self, profile: Profile, result: Result<(), BluetoothError>) {
+        match result {
+            Ok(()) => self.succeeded.push(profile),
+            Err(e) => {
+                log::error!("Failed to change state of profile {}: {}", profile, e);
+                self.failed.push((profile, e));
+            }
+        }
+    }
+
+    // Операция считается неудачной, только если не удалось ни одного профиля;
+    // тогда наружу уходит первая из ошибок вместе с её причиной.
+    pub fn into_result(mut self) -> Result<Self, BluetoothError> {
+        if self.succeeded.is_empty() && !self.failed.is_empty() {
+            Err(self.failed.remove(0).1)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Управление соединением с одной гарнитурой.
+#[async_trait]
+pub trait HeadsetLink: Send + Sync {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError>;
+    async fn state(&self) -> Result<LinkState, BluetoothError>;
+    async fn set_profile(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError>;
+    async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError>;
+    async fn disconnect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError>;
+}
+
+#[async_trait]
+impl<T: HeadsetLink + ?Sized> HeadsetLink for Arc<T> {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+        (**self).find_device().await
+    }
+
+    async fn state(&self) -> Result<LinkState, BluetoothError> {
+        (**self).state().await
+    }
+
+    async fn set_profile(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError> {
+        (**self).set_profile(profile, enabled).await
+    }
+
+    async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        (**self).connect(profiles).await
+    }
+
+    async fn disconnect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        (**self).disconnect(profiles).await
+    }
+}
+
+pub type LinkFactory = Box<dyn Fn(BluetoothAddress) -> Box<dyn HeadsetLink> + Send + Sync>;
+
+pub fn default_link(device_address: BluetoothAddress) -> Box<dyn HeadsetLink> {
+    #[cfg(windows)]
+    {
+        Box::new(BluetoothController::new(device_address))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(BluezController::new(device_address))
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        log::warn!("No native Bluetooth backend on this platform, devices will never be found");
+        Box::new(NoBluetooth(device_address))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+struct NoBluetooth(BluetoothAddress);
+
+#[cfg(not(any(windows, target_os = "linux")))]
+#[async_trait]
+impl HeadsetLink for NoBluetooth {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+        Err(BluetoothError::DeviceNotFound { address: self.0 })
+    }
+
+    async fn state(&self) -> Result<LinkState, BluetoothError> {
+        Err(BluetoothError::DeviceNotFound { address: self.0 })
+    }
+
+    async fn set_profile(&self, _profile: Profile, _enabled: bool) -> Result<(), BluetoothError> {
+        Err(BluetoothError::DeviceNotFound { address: self.0 })
+    }
+
+    async fn connect(&self, _profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        Err(BluetoothError::DeviceNotFound { address: self.0 })
+    }
+
+    async fn disconnect(&self, _profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        Err(BluetoothError::DeviceNotFound { address: self.0 })
+    }
+}
+
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Базовый UUID Bluetooth: 0000xxxx-0000-1000-8000-00805F9B34FB.
+pub const BASE_UUID_SUFFIX: &str = "-0000-1000-8000-00805f9b34fb";
+#[cfg(windows)]
+pub const BASE_UUID_TAIL: [u8; 8] = [0x80, 0x00, 0x00, 0x80, 0x5F, 0x9B, 0x34, 0xFB];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Profile {
+    Headset,
+    A2dpSource,
+    A2dpSink,
+    AvrcpTarget,
+    Avrcp,
+    AvrcpController,
+    HeadsetAudioGateway,
+    HandsFree,
+    HandsFreeAudioGateway,
+}
+
+struct KnownService {
+    profile: Profile,
+    short_uuid: u16,
+    name: &'static str,
+    aliases: &'static [&'static str],
+}
+
+const KNOWN_SERVICES: &[KnownService] = &[
+    KnownService { profile: Profile::Headset, short_uuid: 0x1108, name: "headset", aliases: &["hsp", "hsp-hs"] },
+    KnownService { profile: Profile::A2dpSource, short_uuid: 0x110A, name: "a2dp-source", aliases: &["audio-source"] },
+    KnownService { profile: Profile::A2dpSink, short_uuid: 0x110B, name: "a2dp-sink", aliases: &["a2dp", "audio-sink"] },
+    KnownService { profile: Profile::AvrcpTarget, short_uuid: 0x110C, name: "avrcp-target", aliases: &[] },
+    KnownService { profile: Profile::Avrcp, short_uuid: 0x110E, name: "avrcp", aliases: &["avrcp-remote"] },
+    KnownService { profile: Profile::AvrcpController, short_uuid: 0x110F, name: "avrcp-controller", aliases: &[] },
+    KnownService { profile: Profile::HeadsetAudioGateway, short_uuid: 0x1112, name: "headset-gateway", aliases: &["hsp-ag"] },
+    KnownService { profile: Profile::HandsFree, short_uuid: 0x111E, name: "handsfree", aliases: &["hfp", "hands-free", "hfp-hf"] },
+    KnownService { profile: Profile::HandsFreeAudioGateway, short_uuid: 0x111F, name: "handsfree-gateway", aliases: &["hfp-ag"] },
+];
+
+impl Profile {
+    fn service(&self) -> &'static KnownService {
+        KNOWN_SERVICES
+            .iter()
+            .find(|service| service.profile == *self)
+            .expect("every profile has an entry in KNOWN_SERVICES")
+    }
+
+    pub fn short_uuid(&self) -> u16 {
+        self.service().short_uuid
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.service().name
+    }
+
+    pub fn uuid(&self) -> String {
+        format!("{:08x}{}", self.short_uuid(), BASE_UUID_SUFFIX)
+    }
+
+    pub fn from_short_uuid(short_uuid: u16) -> Option<Self> {
+        KNOWN_SERVICES
+            .iter()
+            .find(|service| service.short_uuid == short_uuid)
+            .map(|service| service.profile)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Принимает имя профиля (`a2dp`, `hfp`, ...), короткий UUID (`0x110B`)
+/// или полный UUID на базе Bluetooth Base UUID.
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+
+        if let Some(service) = KNOWN_SERVICES
+            .iter()
+            .find(|service| service.name == value || service.aliases.contains(&value.as_str()))
+        {
+            return Ok(service.profile);
+        }
+
+        let short_uuid = if let Some(prefix) = value.strip_suffix(BASE_UUID_SUFFIX) {
+            u32::from_str_radix(prefix, 16).ok().and_then(|v| u16::try_from(v).ok())
+        } else {
+            let hex = value.strip_prefix("0x").unwrap_or(&value);
+            if hex.len() == 4 { u16::from_str_radix(hex, 16).ok() } else { None }
+        };
+
+        short_uuid
+            .and_then(Profile::from_short_uuid)
+            .ok_or_else(|| format!("Unknown Bluetooth profile: {}", value))
+    }
+}
+
+impl Serialize for Profile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Profile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_aliases_in_any_case() {
+        assert_eq!("a2dp-sink".parse(), Ok(Profile::A2dpSink));
+        assert_eq!("A2DP".parse(), Ok(Profile::A2dpSink));
+        assert_eq!(" hfp ".parse(), Ok(Profile::HandsFree));
+        assert_eq!("Hands-Free".parse(), Ok(Profile::HandsFree));
+        assert_eq!("hsp-ag".parse(), Ok(Profile::HeadsetAudioGateway));
+    }
+
+    #[test]
+    fn parses_short_uuids() {
+        assert_eq!("0x110B".parse(), Ok(Profile::A2dpSink));
+        assert_eq!("111e".parse(), Ok(Profile::HandsFree));
+        assert!("0x1234".parse::<Profile>().is_err());
+        assert!("0x110".parse::<Profile>().is_err());
+    }
+
+    #[test]
+    fn parses_full_uuids_on_the_base_uuid() {
+        assert_eq!("0000110B-0000-1000-8000-00805F9B34FB".parse(), Ok(Profile::A2dpSink));
+        assert_eq!("0000111f-0000-1000-8000-00805f9b34fb".parse(), Ok(Profile::HandsFreeAudioGateway));
+        // Не на базовом UUID: такой профиль нам неизвестен.
+        assert!("0000110b-0000-1000-8000-00805f9b34fc".parse::<Profile>().is_err());
+        assert!("0001110b-0000-1000-8000-00805f9b34fb".parse::<Profile>().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!("bogus".parse::<Profile>(), Err(String::from("Unknown Bluetooth profile: bogus")));
+    }
+
+    #[test]
+    fn uuid_and_name_round_trip() {
+        for service in KNOWN_SERVICES {
+            let profile = service.profile;
+            assert_eq!(profile.uuid().parse(), Ok(profile));
+            assert_eq!(profile.to_string().parse(), Ok(profile));
+        }
+    }
+}
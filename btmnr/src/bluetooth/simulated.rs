@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+use async_trait::async_trait;
+use log::info;
+
+use super::{BluetoothAddress, BluetoothError, DeviceInfo, HeadsetLink, LinkState, Profile, ProfileReport};
+
+/// Гарнитура, живущая целиком в памяти процесса.
+pub struct SimulatedHeadset {
+    device_address: BluetoothAddress,
+    present: AtomicBool,
+    paired: AtomicBool,
+    enabled_profiles: Mutex<HashSet<Profile>>,
+    failures_left: AtomicU32,
+    profile_failures_left: AtomicU32,
+    connect_calls: AtomicU32,
+    disconnect_calls: AtomicU32,
+    // Сколько длятся подключение и отключение; по tokio::time, как и цикл.
+    latency: Mutex<Duration>,
+}
+
+impl SimulatedHeadset {
+    pub fn new(device_address: BluetoothAddress) -> Self {
+        Self {
+            device_address,
+            present: AtomicBool::new(true),
+            paired: AtomicBool::new(true),
+            enabled_profiles: Mutex::new(HashSet::new()),
+            failures_left: AtomicU32::new(0),
+            profile_failures_left: AtomicU32::new(0),
+            connect_calls: AtomicU32::new(0),
+            disconnect_calls: AtomicU32::new(0),
+            latency: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn set_present(&self, present: bool) {
+        self.present.store(present, Ordering::SeqCst);
+        if !present {
+            self.enabled_profiles.lock().unwrap().clear();
+        }
+    }
+
+    /// Несопряжённое устройство отказывает в подключении постоянной ошибкой.
+    pub fn set_paired(&self, paired: bool) {
+        self.paired.store(paired, Ordering::SeqCst);
+    }
+
+    /// Следующие `count` подключений завершатся временной ошибкой.
+    pub fn fail_next_connects(&self, count: u32) {
+        self.failures_left.store(count, Ordering::SeqCst);
+    }
+
+    /// Следующие `count` вызовов `set_profile` завершатся ошибкой.
+    pub fn fail_next_profile_changes(&self, count: u32) {
+        self.profile_failures_left.store(count, Ordering::SeqCst);
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    pub fn is_connected(&self) -> bool {
+        !self.enabled_profiles.lock().unwrap().is_empty()
+    }
+
+    pub fn enabled_profiles(&self) -> HashSet<Profile> {
+        self.enabled_profiles.lock().unwrap().clone()
+    }
+
+    pub fn connect_calls(&self) -> u32 {
+        self.connect_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn disconnect_calls(&self) -> u32 {
+        self.disconnect_calls.load(Ordering::SeqCst)
+    }
+
+    async fn delay(&self) {
+        let latency = *self.latency.lock().unwrap();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    fn toggle(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError> {
+        self.check_present()?;
+        let mut profiles = self.enabled_profiles.lock().unwrap();
+        if enabled {
+            profiles.insert(profile);
+        } else {
+            profiles.remove(&profile);
+        }
+        Ok(())
+    }
+
+    fn check_present(&self) -> Result<(), BluetoothError> {
+        if self.present.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(BluetoothError::DeviceNotFound { address: self.device_address })
+        }
+    }
+}
+
+#[async_trait]
+impl HeadsetLink for SimulatedHeadset {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+        self.check_present()?;
+        Ok(DeviceInfo {
+            address: self.device_address,
+            name: String::from("Simulated headset"),
+            connected: self.is_connected(),
+            authenticated: self.paired.load(Ordering::SeqCst),
+            remembered: true,
+        })
+    }
+
+    async fn state(&self) -> Result<LinkState, BluetoothError> {
+        self.check_present()?;
+        Ok(if self.is_connected() { LinkState::Connected } else { LinkState::Disconnected })
+    }
+
+    async fn set_profile(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError> {
+        let failures = self.profile_failures_left.load(Ordering::SeqCst);
+        if failures > 0 {
+            self.profile_failures_left.store(failures - 1, Ordering::SeqCst);
+            return Err(BluetoothError::ServiceStateError { address: self.device_address, profile, source: None });
+        }
+        self.toggle(profile, enabled)
+    }
+
+    async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        self.connect_calls.fetch_add(1, Ordering::SeqCst);
+        self.delay().await;
+        self.check_present()?;
+
+        if !self.paired.load(Ordering::SeqCst) {
+            return Err(BluetoothError::AuthenticationError {
+                address: self.device_address,
+                source: None,
+            });
+        }
+
+        let failures = self.failures_left.load(Ordering::SeqCst);
+        if failures > 0 {
+            self.failures_left.store(failures - 1, Ordering::SeqCst);
+            return Err(BluetoothError::DeviceNotFound { address: self.device_address });
+        }
+
+        let mut report = ProfileReport::default();
+        for &profile in profiles {
+            report.record(profile, self.toggle(profile, true));
+        }
+        info!("Simulated device {} connected", self.device_address);
+        report.into_result()
+    }
+
+    async fn disconnect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        self.disconnect_calls.fetch_add(1, Ordering::SeqCst);
+        self.delay().await;
+        self.check_present()?;
+
+        let mut report = ProfileReport::default();
+        for &profile in profiles {
+            report.record(profile, self.toggle(profile, false));
+        }
+        info!("Simulated device {} disconnected", self.device_address);
+        report.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use crate::bluetooth::{BluetoothError, HeadsetLink, LinkState, Profile, SimulatedHeadset};
+
+    fn headset() -> SimulatedHeadset {
+        SimulatedHeadset::new("00:11:22:33:44:55".parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn connect_enables_requested_profiles() {
+        let headset = headset();
+        let report = headset.connect(&[Profile::A2dpSink, Profile::HandsFree]).await.unwrap();
+
+        assert_eq!(report.succeeded, [Profile::A2dpSink, Profile::HandsFree]);
+        assert_eq!(headset.enabled_profiles(), HashSet::from([Profile::A2dpSink, Profile::HandsFree]));
+        assert_eq!(headset.state().await.unwrap(), LinkState::Connected);
+
+        headset.disconnect(&[Profile::A2dpSink, Profile::HandsFree]).await.unwrap();
+        assert_eq!(headset.state().await.unwrap(), LinkState::Disconnected);
+        assert_eq!((headset.connect_calls(), headset.disconnect_calls()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn scripted_failures_are_consumed_one_per_connect() {
+        let headset = headset();
+        headset.fail_next_connects(2);
+
+        for _ in 0..2 {
+            let error = headset.connect(&[Profile::A2dpSink]).await.unwrap_err();
+            assert!(error.is_transient());
+        }
+        assert!(headset.connect(&[Profile::A2dpSink]).await.is_ok());
+        assert_eq!(headset.connect_calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn unpaired_device_fails_permanently() {
+        let headset = headset();
+        headset.set_paired(false);
+
+        let error = headset.connect(&[Profile::A2dpSink]).await.unwrap_err();
+        assert!(matches!(error, BluetoothError::AuthenticationError { .. }));
+        assert!(!error.is_transient());
+        assert!(!headset.find_device().await.unwrap().authenticated);
+    }
+
+    #[tokio::test]
+    async fn absent_device_is_not_found_and_drops_profiles() {
+        let headset = headset();
+        headset.connect(&[Profile::A2dpSink]).await.unwrap();
+        headset.set_present(false);
+
+        assert!(headset.enabled_profiles().is_empty());
+        assert!(matches!(headset.find_device().await, Err(BluetoothError::DeviceNotFound { .. })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency_is_measured_on_the_tokio_clock() {
+        let headset = headset();
+        headset.set_latency(Duration::from_secs(5));
+
+        let started = tokio::time::Instant::now();
+        headset.connect(&[Profile::A2dpSink]).await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,193 @@
+use windows::Win32::Devices::Bluetooth::{
+    BluetoothFindFirstDevice,
+    BluetoothFindDeviceClose,
+    BLUETOOTH_DEVICE_INFO,
+    BLUETOOTH_DEVICE_SEARCH_PARAMS,
+    BluetoothAuthenticateDevice,
+    BluetoothSetServiceState,
+    BluetoothFindNextDevice,
+};
+use windows::Win32::Foundation::BOOL;
+use windows::core::GUID;
+use std::mem::zeroed;
+use async_trait::async_trait;
+use log::{error, info};
+
+use super::{BluetoothAddress, BluetoothError, DeviceInfo, HeadsetLink, LinkState, Profile, ProfileReport, Win32Error};
+use super::profiles::BASE_UUID_TAIL;
+
+fn service_guid(profile: Profile) -> GUID {
+    GUID::from_values(profile.short_uuid() as u32, 0x0000, 0x1000, BASE_UUID_TAIL)
+}
+
+unsafe fn set_service_state(
+    device_info: &BLUETOOTH_DEVICE_INFO,
+    profile: Profile,
+    enabled: bool,
+) -> Result<(), BluetoothError> {
+    let flags = if enabled { 1 } else { 0 };
+    BluetoothSetServiceState(None, device_info, &service_guid(profile), flags)
+        .map_err(|e| {
+            error!("Failed to {} {} service: {:?}", if enabled { "enable" } else { "disable" }, profile, e);
+            BluetoothError::ServiceStateError {
+                address: address_of(device_info),
+                profile,
+                source: Some(Win32Error::from(&e).into()),
+            }
+        })
+}
+
+/// Закрывает дескриптор поиска при любом выходе, в том числе по ошибке.
+struct FindHandle(isize);
+
+impl Drop for FindHandle {
+    fn drop(&mut self) {
+        unsafe {
+            BluetoothFindDeviceClose(self.0);
+        }
+    }
+}
+
+pub struct BluetoothController {
+    device_address: BluetoothAddress,
+}
+
+impl BluetoothController {
+    pub fn new(device_address: BluetoothAddress) -> Self {
+        Self { device_address }
+    }
+}
+
+// Вызовы BluetoothApis блокируют поток: поиск с inquiry длится секундами,
+// а BluetoothAuthenticateDevice может ждать ответа устройства ещё дольше.
+// Поэтому они выполняются в пуле блокирующих потоков. При остановке
+// сервиса ожидание бросается по drain_timeout, но сам вызов продолжается,
+// пока runtime не закрыт через manager::shutdown_runtime.
+async fn blocking<T, F>(operation: F) -> Result<T, BluetoothError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, BluetoothError> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(operation).await {
+        Ok(result) => result,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+unsafe fn find_device_info(
+    address: BluetoothAddress,
+    include_inquiry: bool,
+) -> Result<(FindHandle, BLUETOOTH_DEVICE_INFO), BluetoothError> {
+    let mut params: BLUETOOTH_DEVICE_SEARCH_PARAMS = zeroed();
+    params.dwSize = std::mem::size_of::<BLUETOOTH_DEVICE_SEARCH_PARAMS>() as u32;
+    params.fReturnAuthenticated = BOOL::from(true);
+    params.fReturnConnected = BOOL::from(true);
+    params.fReturnRemembered = BOOL::from(true);
+    params.fIssueInquiry = BOOL::from(include_inquiry);
+    params.cTimeoutMultiplier = 1;
+
+    let mut device_info: BLUETOOTH_DEVICE_INFO = zeroed();
+    device_info.dwSize = std::mem::size_of::<BLUETOOTH_DEVICE_INFO>() as u32;
+
+    let device_handle = BluetoothFindFirstDevice(&params, &mut device_info)
+        .map(FindHandle)
+        .map_err(|e| {
+            error!("Failed to start device enumeration: {:?}", e);
+            BluetoothError::EnumerationError { source: Some(Win32Error::from(&e).into()) }
+        })?;
+
+    while device_info.Address.Anonymous.rgBytes != address.to_le_bytes() {
+        if BluetoothFindNextDevice(device_handle.0, &mut device_info).is_err() {
+            return Err(BluetoothError::DeviceNotFound { address });
+        }
+    }
+
+    Ok((device_handle, device_info))
+}
+
+fn address_of(device_info: &BLUETOOTH_DEVICE_INFO) -> BluetoothAddress {
+    unsafe { BluetoothAddress::from_le_bytes(device_info.Address.Anonymous.rgBytes) }
+}
+
+fn to_device_info(device_info: &BLUETOOTH_DEVICE_INFO) -> DeviceInfo {
+    let name_len = device_info.szName.iter().position(|&c| c == 0).unwrap_or(device_info.szName.len());
+    DeviceInfo {
+        address: address_of(device_info),
+        name: String::from_utf16_lossy(&device_info.szName[..name_len]),
+        connected: device_info.fConnected.as_bool(),
+        authenticated: device_info.fAuthenticated.as_bool(),
+        remembered: device_info.fRemembered.as_bool(),
+    }
+}
+
+#[async_trait]
+impl HeadsetLink for BluetoothController {
+    async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+        let address = self.device_address;
+        blocking(move || unsafe {
+            let (_handle, device_info) = find_device_info(address, false)?;
+            Ok(to_device_info(&device_info))
+        })
+        .await
+    }
+
+    async fn state(&self) -> Result<LinkState, BluetoothError> {
+        let device = self.find_device().await?;
+        Ok(if device.connected { LinkState::Connected } else { LinkState::Disconnected })
+    }
+
+    async fn set_profile(&self, profile: Profile, enabled: bool) -> Result<(), BluetoothError> {
+        let address = self.device_address;
+        blocking(move || unsafe {
+            let (_handle, device_info) = find_device_info(address, false)?;
+            set_service_state(&device_info, profile, enabled)
+        })
+        .await
+    }
+
+    async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        let (address, profiles) = (self.device_address, profiles.to_vec());
+        blocking(move || unsafe {
+            let (_handle, device_info) = find_device_info(address, true)?;
+
+            info!("Found target device, attempting to authenticate");
+            BluetoothAuthenticateDevice(None, None, &device_info, None)
+                .map_err(|e| {
+                    error!("Authentication failed for device {}: {:?}", address, e);
+                    BluetoothError::AuthenticationError {
+                        address,
+                        source: Some(Win32Error::from(&e).into()),
+                    }
+                })?;
+
+            let mut report = ProfileReport::default();
+            for profile in profiles {
+                info!("Enabling {} service", profile);
+                report.record(profile, set_service_state(&device_info, profile, true));
+            }
+
+            let report = report.into_result()?;
+            info!("Successfully connected to device {}", address);
+            Ok(report)
+        })
+        .await
+    }
+
+    async fn disconnect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+        let (address, profiles) = (self.device_address, profiles.to_vec());
+        blocking(move || unsafe {
+            let (_handle, device_info) = find_device_info(address, false)?;
+
+            let mut report = ProfileReport::default();
+            for profile in profiles {
+                info!("Disabling {} service", profile);
+                report.record(profile, set_service_state(&device_info, profile, false));
+            }
+
+            let report = report.into_result()?;
+            info!("Successfully disconnected from device {}", address);
+            Ok(report)
+        })
+        .await
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::bluetooth::{AddressParseError, BluetoothAddress};
+use crate::control::ManagerCommand;
+
+pub const USAGE: &str = "\
+Usage: btmnr [--config <path>] [COMMAND]
+
+Commands:
+  run                    Monitor audio in the foreground
+  service                Run under the Windows service control manager
+  connect <address>      Connect a device once and exit
+  disconnect <address>   Disconnect a device once and exit
+  list-devices           Show configured devices and whether they are reachable
+  status                 Show current audio activity and link state of each device
+  check-config           Validate the configuration file
+  convert-config <path>  Write the configuration to <path> in the format of its extension
+  pause                  Suspend automatic connect and disconnect in the running service
+  resume                 Resume automatic connect and disconnect
+  connect-now            Ask the running service to connect the best device now
+  disconnect-now         Ask the running service to disconnect its devices now
+  reload-config          Ask the running service to reload its configuration
+  install                Register the Windows service or the systemd user unit
+  uninstall              Remove the Windows service or the systemd user unit
+  help                   Show this message
+
+Options:
+  --config <path>        Configuration file; overrides BTMNR_CONFIG and the platform default
+
+Without a command the binary runs as a Windows service on Windows
+and in the foreground elsewhere.";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    Service,
+    Connect { address: BluetoothAddress },
+    Disconnect { address: BluetoothAddress },
+    ListDevices,
+    Status,
+    CheckConfig,
+    ConvertConfig { output: PathBuf },
+    Control(ManagerCommand),
+    Install,
+    Uninstall,
+    Help,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CliError {
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("{command} requires <{argument}>")]
+    MissingArgument { command: &'static str, argument: &'static str },
+    #[error("Unexpected argument: {0}")]
+    UnexpectedArgument(String),
+    #[error("Invalid Bluetooth address {address:?}: {source}")]
+    InvalidAddress { address: String, source: AddressParseError },
+}
+
+/// Разбирает аргументы командной строки без имени программы.
+/// `--config` можно указать в любом месте, в том числе после команды.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, CliError> {
+    let mut config = None;
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            config = Some(PathBuf::from(required(&mut args, "--config", "path")?));
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            config = Some(PathBuf::from(path));
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    let command = parse_command(positional)?;
+    Ok(Args { config, command })
+}
+
+fn parse_command(args: Vec<String>) -> Result<Command, CliError> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        // SCM запускает службу без аргументов, поэтому старые установки
+        // продолжают работать как раньше.
+        None if cfg!(windows) => Command::Service,
+        None => Command::Run,
+        Some("run") => Command::Run,
+        Some("service") => Command::Service,
+        Some("connect") => Command::Connect { address: address(required(&mut args, "connect", "address")?)? },
+        Some("disconnect") => Command::Disconnect { address: address(required(&mut args, "disconnect", "address")?)? },
+        Some("list-devices") => Command::ListDevices,
+        Some("status") => Command::Status,
+        Some("check-config") => Command::CheckConfig,
+        Some("convert-config") => Command::ConvertConfig {
+            output: PathBuf::from(required(&mut args, "convert-config", "path")?),
+        },
+        Some("install") => Command::Install,
+        Some("uninstall") => Command::Uninstall,
+        Some("help" | "-h" | "--help") => Command::Help,
+        Some(other) => match other.parse() {
+            Ok(command) => Command::Control(command),
+            Err(_) => return Err(CliError::UnknownCommand(other.to_string())),
+        },
+    };
+
+    match args.next() {
+        Some(extra) => Err(CliError::UnexpectedArgument(extra)),
+        None => Ok(command),
+    }
+}
+
+fn required(
+    args: &mut impl Iterator<Item = String>,
+    command: &'static str,
+    argument: &'static str,
+) -> Result<String, CliError> {
+    args.next().ok_or(CliError::MissingArgument { command, argument })
+}
+
+fn address(text: String) -> Result<BluetoothAddress, CliError> {
+    text.parse().map_err(|source| CliError::InvalidAddress { address: text, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn config_may_come_before_or_after_the_command() {
+        let expected = Args { config: Some(PathBuf::from("/etc/btmnr.toml")), command: Command::Status };
+
+        assert_eq!(parse_args(&["--config", "/etc/btmnr.toml", "status"]), Ok(expected.clone()));
+        assert_eq!(parse_args(&["status", "--config", "/etc/btmnr.toml"]), Ok(expected.clone()));
+        assert_eq!(parse_args(&["status", "--config=/etc/btmnr.toml"]), Ok(expected));
+        assert_eq!(
+            parse_args(&["status", "--config"]),
+            Err(CliError::MissingArgument { command: "--config", argument: "path" })
+        );
+    }
+
+    #[test]
+    fn commands_take_their_arguments() {
+        let address: BluetoothAddress = "00:11:22:33:44:55".parse().unwrap();
+
+        assert_eq!(parse_args(&["connect", "00-11-22-33-44-55"]).unwrap().command, Command::Connect { address });
+        assert_eq!(parse_args(&["disconnect", "00:11:22:33:44:55"]).unwrap().command, Command::Disconnect { address });
+        assert_eq!(
+            parse_args(&["convert-config", "out.yaml"]).unwrap().command,
+            Command::ConvertConfig { output: PathBuf::from("out.yaml") }
+        );
+        assert_eq!(parse_args(&["pause"]).unwrap().command, Command::Control(ManagerCommand::Pause));
+    }
+
+    #[test]
+    fn missing_and_invalid_addresses_are_rejected() {
+        assert_eq!(
+            parse_args(&["connect"]),
+            Err(CliError::MissingArgument { command: "connect", argument: "address" })
+        );
+        assert_eq!(
+            parse_args(&["disconnect", "00:11:22"]),
+            Err(CliError::InvalidAddress { address: String::from("00:11:22"), source: AddressParseError::OctetCount(3) })
+        );
+    }
+
+    #[test]
+    fn extra_and_unknown_arguments_are_rejected() {
+        assert_eq!(
+            parse_args(&["status", "now"]),
+            Err(CliError::UnexpectedArgument(String::from("now")))
+        );
+        assert_eq!(
+            parse_args(&["connect", "00:11:22:33:44:55", "extra"]),
+            Err(CliError::UnexpectedArgument(String::from("extra")))
+        );
+        assert_eq!(parse_args(&["frobnicate"]), Err(CliError::UnknownCommand(String::from("frobnicate"))));
+    }
+
+    #[test]
+    fn no_command_uses_the_platform_default() {
+        let expected = if cfg!(windows) { Command::Service } else { Command::Run };
+        assert_eq!(parse_args(&[]).unwrap().command, expected);
+        assert_eq!(parse_args(&["--config=btmnr.json"]).unwrap().command, expected);
+    }
+}
@@ -0,0 +1,243 @@
+use std::path::Path;
+
+use crate::audio;
+use crate::bluetooth::{self, BluetoothAddress, Profile, ProfileReport};
+use crate::config::{Config, ConfigError, ConfigFormat, ConfigManager, DeviceConfig};
+use crate::control::ManagerCommand;
+use crate::error::Result;
+use crate::switching::AudioMode;
+
+// Разовые команды используют тот же ConfigManager и те же реализации
+// HeadsetLink, что и сам сервис, поэтому ведут себя одинаково с ним.
+
+pub async fn connect(config_manager: &ConfigManager, address: BluetoothAddress) -> Result<()> {
+    let device = device_config(&config_manager.get_config(), address);
+    let link = bluetooth::default_link(device.address);
+    let report = link.connect(device.profiles_for(AudioMode::Listening)).await?;
+    print_report("Connected", device.address, &report);
+    Ok(())
+}
+
+pub async fn disconnect(config_manager: &ConfigManager, address: BluetoothAddress) -> Result<()> {
+    let device = device_config(&config_manager.get_config(), address);
+    let link = bluetooth::default_link(device.address);
+    let report = link.disconnect(&device.all_profiles()).await?;
+    print_report("Disconnected", device.address, &report);
+    Ok(())
+}
+
+pub async fn list_devices(config_manager: &ConfigManager) -> Result<()> {
+    for device in &config_manager.get_config().devices {
+        let link = bluetooth::default_link(device.address);
+        match link.find_device().await {
+            Ok(info) => {
+                let mut flags = vec![if info.connected { "connected" } else { "disconnected" }];
+                if info.authenticated {
+                    flags.push("paired");
+                }
+                if info.remembered {
+                    flags.push("remembered");
+                }
+                println!("{}  {:<24}  priority {:<3}  {}", info.address, info.name, device.priority, flags.join(", "));
+            }
+            Err(e) => println!(
+                "{}  {:<24}  priority {:<3}  unavailable [{}]",
+                device.address,
+                device.name.as_deref().unwrap_or(""),
+                device.priority,
+                e.code()
+            ),
+        }
+    }
+    Ok(())
+}
+
+pub async fn status(config_manager: &ConfigManager) -> Result<()> {
+    let config = config_manager.get_config();
+    let policy = config.activity_policy();
+    let source = audio::default_source();
+    println!("Audio playing:      {}", yes_no(source.is_audio_playing(&policy)?));
+    println!("Microphone in use:  {}", yes_no(source.is_microphone_in_use(&policy)?));
+
+    for device in &config.devices {
+        let link = bluetooth::default_link(device.address);
+        match link.state().await {
+            Ok(state) => println!("{}  {:?}", device.address, state),
+            Err(e) => println!("{}  unavailable [{}]: {}", device.address, e.code(), e),
+        }
+    }
+    print_service_status();
+    Ok(())
+}
+
+// Попытки и время следующего повтора знает только работающий сервис.
+#[cfg(unix)]
+fn print_service_status() {
+    let status = match crate::ipc::socket_path() {
+        Ok(path) => crate::ipc::query_status(&path),
+        Err(e) => Err(e.into()),
+    };
+    match status {
+        Ok(devices) => {
+            println!("Service:");
+            for device in devices {
+                println!("  {}", device);
+            }
+        }
+        Err(e) => println!("Service:            not reachable ({})", e),
+    }
+}
+
+#[cfg(not(unix))]
+fn print_service_status() {
+    println!("Service:            see the service log for connection attempts");
+}
+
+/// Выводит каждую проблему конфигурации отдельной строкой с путём к полю.
+pub fn check_config(path: &Path) -> Result<()> {
+    match Config::load(path) {
+        Ok(config) => {
+            println!("{} is valid: {} device(s) configured", path.display(), config.devices.len());
+            Ok(())
+        }
+        Err(ConfigError::Validation(errors)) => {
+            for error in &errors {
+                println!("{}: {}", path.display(), error);
+            }
+            Err(ConfigError::Invalid(format!("{} has {} problem(s)", path.display(), errors.len())).into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Переписывает конфигурацию из `input` в `output`; формат каждого файла
+/// определяется по расширению.
+pub fn convert_config(input: &Path, output: &Path) -> Result<()> {
+    // Перезапись исходного файла молча уничтожила бы его комментарии.
+    if input == output {
+        return Err(ConfigError::Invalid(format!("{} is the configuration being converted", output.display())).into());
+    }
+    let config = Config::load(input)?;
+    config.save(output)?;
+    println!(
+        "Converted {} ({}) to {} ({})",
+        input.display(),
+        ConfigFormat::from_path(input)?,
+        output.display(),
+        ConfigFormat::from_path(output)?
+    );
+    Ok(())
+}
+
+/// Передаёт команду работающему сервису: через SCM на Windows,
+/// через локальный сокет на остальных платформах.
+#[cfg(windows)]
+pub fn send_control(command: ManagerCommand) -> Result<()> {
+    use windows_service::{
+        service::{ServiceAccess, ServiceControl, UserEventCode},
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        crate::install::SERVICE_NAME,
+        ServiceAccess::PAUSE_CONTINUE | ServiceAccess::USER_DEFINED_CONTROL,
+    )?;
+    match command {
+        ManagerCommand::Pause => {
+            service.pause()?;
+        }
+        ManagerCommand::Resume => {
+            service.resume()?;
+        }
+        command => {
+            let code = command.control_code().ok_or(crate::error::Error::Unsupported(command.as_str()))?;
+            service.notify(ServiceControl::UserEvent(UserEventCode::from_raw(code)?))?;
+        }
+    }
+    println!("Sent {} to service {}", command, crate::install::SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn send_control(command: ManagerCommand) -> Result<()> {
+    let path = crate::ipc::socket_path()?;
+    crate::ipc::send(&path, command)?;
+    println!("Sent {} to the service listening on {}", command, path.display());
+    Ok(())
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn send_control(command: ManagerCommand) -> Result<()> {
+    Err(crate::error::Error::Unsupported(command.as_str()))
+}
+
+// Неизвестное устройство обслуживается с профилями по умолчанию.
+fn device_config(config: &Config, address: BluetoothAddress) -> DeviceConfig {
+    config
+        .devices
+        .iter()
+        .find(|device| device.address == address)
+        .cloned()
+        .unwrap_or_else(|| DeviceConfig::new(address))
+}
+
+fn print_report(action: &str, address: BluetoothAddress, report: &ProfileReport) {
+    let succeeded: Vec<String> = report.succeeded.iter().map(Profile::to_string).collect();
+    println!("{} {}: {}", action, address, succeeded.join(", "));
+    for (profile, e) in &report.failed {
+        println!("  {} failed [{}]: {}", profile, e.code(), e);
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn check_config_reports_every_problem() {
+        let dir = TempDir::new("check");
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{ "version": 1, "devices": [{ "address": "00:11:22:33:44:55" }] }"#).unwrap();
+        assert!(check_config(&path).is_ok());
+
+        std::fs::write(&path, r#"{ "version": 1, "inactivity_timeout": 0, "devices": [{ "address": "00:11" }] }"#).unwrap();
+        let error = check_config(&path).unwrap_err();
+        assert!(error.to_string().contains("has 2 problem(s)"), "{}", error);
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(check_config(&path), Err(crate::error::Error::Config(ConfigError::Parse { .. }))));
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let dir = TempDir::new("convert");
+        let json = dir.join("config.json");
+        let mut config = Config { inactivity_timeout: 90, ..Config::default() };
+        config.devices.push(DeviceConfig::new("00:11:22:33:44:55".parse().unwrap()));
+        config.save(&json).unwrap();
+
+        convert_config(&json, &dir.join("config.toml")).unwrap();
+        convert_config(&dir.join("config.toml"), &dir.join("config.yaml")).unwrap();
+
+        let converted = Config::load(&dir.join("config.yaml")).unwrap();
+        assert_eq!(converted.inactivity_timeout, 90);
+        assert_eq!(converted.devices, config.devices);
+        assert!(std::fs::read_to_string(dir.join("config.toml")).unwrap().contains("[[devices]]"));
+    }
+
+    #[test]
+    fn refuses_to_convert_onto_itself() {
+        let dir = TempDir::new("convert");
+        let json = dir.join("config.json");
+        std::fs::write(&json, "{}").unwrap();
+
+        assert!(convert_config(&json, &json).is_err());
+        assert_eq!(std::fs::read_to_string(&json).unwrap(), "{}");
+    }
+}
@@ -1,116 +1,953 @@
-use notify::{Watcher, RecursiveMode, watcher};
-use serde::{Deserialize, Serialize};
+use notify::{DebouncedEvent, Watcher, RecursiveMode, watcher};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
+use thiserror::Error;
+
+use crate::audio::{ActivityPolicy, SessionFilter};
+use crate::bluetooth::{BluetoothAddress, Profile};
+use crate::migration::{self, CURRENT_VERSION};
+use crate::retry::RetryPolicy;
+use crate::switching::AudioMode;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {}: {source}", .path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("Failed to write config file {}: {source}", .path.display())]
+    Write { path: PathBuf, source: std::io::Error },
+    #[error("Config version {found} is newer than the supported version {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("Unsupported config file extension: {} (expected .json, .toml or .yaml)", .0.display())]
+    UnknownFormat(PathBuf),
+    #[error("Failed to parse {format} config: {source}")]
+    Parse { format: ConfigFormat, source: Box<dyn std::error::Error + Send + Sync> },
+    #[error("Failed to serialize config as {format}: {source}")]
+    Serialize { format: ConfigFormat, source: Box<dyn std::error::Error + Send + Sync> },
+    #[error("Invalid configuration: {}", join_errors(.0))]
+    Validation(Vec<ValidationError>),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Одна проблема конфигурации с путём к полю, например `devices[1].address`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+fn join_errors(errors: &[ValidationError]) -> String {
+    errors.iter().map(ValidationError::to_string).collect::<Vec<_>>().join("; ")
+}
+
+/// Формат файла конфигурации; определяется по расширению. Проверка после
+/// разбора одна и та же для всех форматов.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            _ => Err(ConfigError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Разбирает файл. Файл текущей версии разбирается напрямую, чтобы
+    /// ошибки указывали на строку; старые сначала проходят через миграции.
+    fn parse(self, text: &str) -> Result<Parsed, ConfigError> {
+        let mut value: serde_json::Value = self.deserialize(text)?;
+        let version = migration::version(&value)?;
+
+        // Неверный адрес остановил бы разбор на первой ошибке и без пути
+        // к полю. Поэтому такие адреса подменяются заглушками, а ошибки
+        // о них сообщаются вместе с остальными ошибками проверки. Это
+        // делается до миграций, чтобы путь указывал на поле в файле.
+        let address_errors = replace_invalid_addresses(&mut value, version);
+        let migrated = migration::migrate(&mut value, version)?;
+        if !address_errors.is_empty() {
+            let config: Config = self.config_from_value(value)?;
+            let mut errors = address_errors;
+            if let Err(ConfigError::Validation(other)) = config.validate() {
+                errors.extend(other);
+            }
+            return Err(ConfigError::Validation(errors));
+        }
+
+        let config = if version == CURRENT_VERSION {
+            self.deserialize(text)?
+        } else {
+            self.config_from_value(value)?
+        };
+        Ok(Parsed { config, version, migrated })
+    }
+
+    fn config_from_value(self, value: serde_json::Value) -> Result<Config, ConfigError> {
+        serde_json::from_value(value).map_err(|e| ConfigError::Parse { format: self, source: e.into() })
+    }
+
+    fn deserialize<T: DeserializeOwned>(self, text: &str) -> Result<T, ConfigError> {
+        let parse_error = |source: Box<dyn std::error::Error + Send + Sync>| ConfigError::Parse { format: self, source };
+        match self {
+            ConfigFormat::Json => serde_json::from_str(text).map_err(|e| parse_error(e.into())),
+            ConfigFormat::Toml => toml::from_str(text).map_err(|e| parse_error(e.into())),
+            ConfigFormat::Yaml => serde_yaml::from_str(text).map_err(|e| parse_error(e.into())),
+        }
+    }
+
+    fn serialize(self, config: &Config) -> Result<String, ConfigError> {
+        let serialize_error =
+            |source: Box<dyn std::error::Error + Send + Sync>| ConfigError::Serialize { format: self, source };
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(config).map_err(|e| serialize_error(e.into())),
+            ConfigFormat::Toml => toml::to_string_pretty(config).map_err(|e| serialize_error(e.into())),
+            ConfigFormat::Yaml => serde_yaml::to_string(config).map_err(|e| serialize_error(e.into())),
+        }
+    }
+}
+
+/// Разобранный файл конфигурации.
+struct Parsed {
+    config: Config,
+    // Версия схемы в самом файле.
+    version: u32,
+    // Миграции изменили содержимое, а не только номер версии; только
+    // такой файл есть смысл переписывать.
+    migrated: bool,
+}
+
+// Заменяет неразборные адреса устройств адресами, которых нет в файле,
+// и возвращает ошибки о них. Заглушки различны, поэтому проверка на
+// повторяющиеся адреса их не задевает.
+fn replace_invalid_addresses(config: &mut serde_json::Value, version: u32) -> Vec<ValidationError> {
+    let Some(config) = config.as_object_mut() else {
+        return Vec::new();
+    };
+
+    let mut addresses: Vec<(String, &mut serde_json::Value)> = Vec::new();
+    for (key, value) in config.iter_mut() {
+        match (key.as_str(), value) {
+            // В версии 0 адрес единственного устройства лежит в корне.
+            ("device_address", value) if version == 0 && !value.is_null() => addresses.push((key.clone(), value)),
+            ("devices", serde_json::Value::Array(devices)) => {
+                for (i, device) in devices.iter_mut().enumerate() {
+                    let Some(device) = device.as_object_mut() else {
+                        continue;
+                    };
+                    for (key, value) in device.iter_mut() {
+                        if key == "address" || key == "device_address" {
+                            addresses.push((format!("devices[{}].{}", i, key), value));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let parse = |value: &serde_json::Value| value.as_str().map(str::parse::<BluetoothAddress>);
+    let used: Vec<BluetoothAddress> = addresses.iter().filter_map(|(_, value)| parse(value)?.ok()).collect();
+    let mut placeholders = (0..=0xFFFF_FFFF_FFFF_u64).rev().map(BluetoothAddress::from).filter(|a| !used.contains(a));
+    let mut errors = Vec::new();
+    for (field, value) in addresses {
+        let message = match parse(value) {
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => format!("{:?} is not a valid Bluetooth address: {}", value.as_str().unwrap_or_default(), e),
+            None => String::from("must be a string like AA:BB:CC:DD:EE:FF"),
+        };
+        errors.push(ValidationError::new(field, message));
+        let placeholder = placeholders.next().expect("fewer devices than addresses");
+        *value = serde_json::Value::String(placeholder.to_string());
+    }
+    errors
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Toml => "TOML",
+            ConfigFormat::Yaml => "YAML",
+        })
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
+    // Версия схемы; старые файлы обновляются модулем migration. Файл без
+    // этого поля имеет версию 0, как и в migration::version.
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default = "default_inactivity_timeout")]
     pub inactivity_timeout: u64,
+    #[serde(default = "default_true")]
     pub auto_connect: bool,
-    pub device_address: String,
+    #[serde(default)]
+    pub peak_threshold: Option<f32>,
+    #[serde(default)]
+    pub ignored_apps: Vec<String>,
+    #[serde(default)]
+    pub trigger_apps: Vec<String>,
+    #[serde(default = "default_microphone_debounce")]
+    pub microphone_debounce: u64,
+    // Отключать подключённые устройства при остановке сервиса.
+    #[serde(default)]
+    pub disconnect_on_stop: bool,
+    // Сколько секунд при остановке ждать завершения текущих операций.
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout: u64,
+    // Таблицы идут последними: в TOML простое значение после таблицы
+    // попало бы в неё, и сериализатор такой порядок отвергает.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeviceConfig {
+    #[serde(alias = "device_address")]
+    pub address: BluetoothAddress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_profiles")]
+    pub profiles: Vec<Profile>,
+    // Если задано, `profiles` используются, пока звук только воспроизводится,
+    // а эти профили включаются, когда какое-то приложение занимает микрофон.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub call_profiles: Vec<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inactivity_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_connect: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+}
+
+impl DeviceConfig {
+    pub fn new(address: BluetoothAddress) -> Self {
+        Self {
+            address,
+            name: None,
+            priority: 0,
+            profiles: default_profiles(),
+            call_profiles: Vec::new(),
+            inactivity_timeout: None,
+            auto_connect: None,
+            retry: None,
+        }
+    }
+
+    pub fn switches_on_microphone(&self) -> bool {
+        !self.call_profiles.is_empty()
+    }
+
+    pub fn profiles_for(&self, mode: AudioMode) -> &[Profile] {
+        match mode {
+            AudioMode::Call if self.switches_on_microphone() => &self.call_profiles,
+            _ => &self.profiles,
+        }
+    }
+
+    pub fn all_profiles(&self) -> Vec<Profile> {
+        let mut profiles = self.profiles.clone();
+        for profile in &self.call_profiles {
+            if !profiles.contains(profile) {
+                profiles.push(*profile);
+            }
+        }
+        profiles
+    }
+}
+
+fn default_inactivity_timeout() -> u64 {
+    300
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_microphone_debounce() -> u64 {
+    3
+}
+
+fn default_drain_timeout() -> u64 {
+    10
+}
+
+// Допустимые значения интервалов в секундах.
+const MAX_INACTIVITY_TIMEOUT: u64 = 24 * 60 * 60;
+const MAX_MICROPHONE_DEBOUNCE: u64 = 60 * 60;
+const MAX_DRAIN_TIMEOUT: u64 = 5 * 60;
+
+fn default_profiles() -> Vec<Profile> {
+    vec![Profile::HandsFree]
 }
 
 #[derive(Clone)]
 pub struct ConfigManager {
+    path: PathBuf,
     current_config: Arc<RwLock<Config>>,
     backup_config: Arc<RwLock<Config>>,
 }
 
 impl ConfigManager {
-    pub fn new() -> Self {
-        let config = Config::load().unwrap_or_default();
-        let manager = ConfigManager {
-            current_config: Arc::new(RwLock::new(config.clone())),
-            backup_config: Arc::new(RwLock::new(config)),
-        };
+    pub fn new(path: PathBuf) -> Self {
+        let config = Config::upgrade(&path).unwrap_or_else(|e| {
+            log::warn!("{}, using default configuration", e);
+            Config::default()
+        });
+        let manager = Self::with_config(path, config);
         manager.start_config_watcher();
         manager
     }
 
+    /// Загружает конфигурацию без отслеживания изменений. Разовым командам
+    /// нужна ошибка, а не молчаливая подмена конфигурацией по умолчанию.
+    pub fn load(path: PathBuf) -> Result<Self, ConfigError> {
+        let config = Config::load(&path)?;
+        Ok(Self::with_config(path, config))
+    }
+
+    pub fn with_config(path: PathBuf, config: Config) -> Self {
+        ConfigManager {
+            path,
+            current_config: Arc::new(RwLock::new(config.clone())),
+            backup_config: Arc::new(RwLock::new(config)),
+        }
+    }
+
     pub fn get_config(&self) -> Config {
         self.current_config.read().unwrap().clone()
     }
 
+    /// Перечитывает файл конфигурации. Если новая конфигурация не загрузилась,
+    /// остаётся предыдущая рабочая.
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        match Config::load(&self.path) {
+            Ok(new_config) => {
+                let mut current = self.current_config.write().unwrap();
+                let mut backup = self.backup_config.write().unwrap();
+                *backup = current.clone();
+                *current = new_config;
+                log::info!("Configuration reloaded successfully");
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("Failed to load new config: {}", e);
+                // Restore from backup
+                let backup = self.backup_config.read().unwrap();
+                let mut current = self.current_config.write().unwrap();
+                *current = backup.clone();
+                log::info!("Restored previous working configuration");
+                Err(e)
+            }
+        }
+    }
+
+    // Следим за каталогом, а не за самим файлом: файла может ещё не быть,
+    // а редакторы часто сохраняют через запись во временный файл и
+    // переименование, после чего наблюдение за старым inode теряется.
     fn start_config_watcher(&self) {
-        let config_clone = self.current_config.clone();
-        let backup_clone = self.backup_config.clone();
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        // При первом запуске нет и каталога по умолчанию, а следить за
+        // несуществующим каталогом нельзя.
+        if !dir.is_dir() {
+            match fs::create_dir_all(dir) {
+                Ok(()) => log::info!("Created configuration directory {}", dir.display()),
+                Err(e) => {
+                    log::error!("Failed to create {}, configuration changes will not be picked up: {}", dir.display(), e);
+                    return;
+                }
+            }
+        }
+        // Наблюдение регистрируется до возврата, чтобы не пропустить
+        // изменения, сделанные сразу после запуска.
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            log::error!("Failed to watch {}: {:?}", dir.display(), e);
+            return;
+        }
 
+        let manager = self.clone();
         std::thread::spawn(move || {
-            let (tx, rx) = std::sync::mpsc::channel();
-            let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
-            watcher.watch("config.json", RecursiveMode::NonRecursive).unwrap();
-
-            loop {
-                match rx.recv() {
-                    Ok(_) => {
-                        match Config::load() {
-                            Ok(new_config) => {
-                                let mut current = config_clone.write().unwrap();
-                                let mut backup = backup_clone.write().unwrap();
-                                *backup = current.clone();
-                                *current = new_config;
-                                log::info!("Configuration reloaded successfully");
-                            }
-                            Err(e) => {
-                                log::error!("Failed to load new config: {}", e);
-                                // Restore from backup
-                                let backup = backup_clone.read().unwrap();
-                                let mut current = config_clone.write().unwrap();
-                                *current = backup.clone();
-                                log::info!("Restored previous working configuration");
-                            }
-                        }
-                    }
-                    Err(e) => log::error!("Watch error: {:?}", e),
+            let _watcher = watcher;
+            // Отправитель живёт в watcher, поэтому recv завершится ошибкой,
+            // только если watcher остановился.
+            while let Ok(event) = rx.recv() {
+                if is_config_change(&event, &manager.path) {
+                    let _ = manager.reload();
                 }
             }
         });
     }
 }
 
+fn is_config_change(event: &DebouncedEvent, config_path: &Path) -> bool {
+    let changed = match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => path,
+        DebouncedEvent::Error(e, _) => {
+            log::error!("Watch error: {:?}", e);
+            return false;
+        }
+        _ => return false,
+    };
+    changed.file_name() == config_path.file_name()
+}
+
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_str = fs::read_to_string("config.json")
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        
-        let config: Config = serde_json::from_str(&config_str)
-            .map_err(|e| format!("Failed to parse config: {}", e))?;
-        
-        config.validate()?;
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::read(path).map(|(parsed, _)| parsed.config)
+    }
+
+    /// Загружает конфигурацию и, если миграции изменили её содержимое,
+    /// сохраняет копию исходного файла рядом с ним (`config.json.v0.bak`)
+    /// и перезаписывает файл в текущем формате. TOML и YAML не
+    /// перезаписываются: сериализатор потерял бы их комментарии.
+    pub fn upgrade(path: &Path) -> Result<Self, ConfigError> {
+        let (Parsed { config, version, migrated }, config_str) = Self::read(path)?;
+        if !migrated {
+            return Ok(config);
+        }
+        if ConfigFormat::from_path(path)? != ConfigFormat::Json {
+            log::warn!(
+                "{} uses config version {} and is upgraded in memory on every load; \
+                 it is left as is to keep its comments, update it to version {} by hand",
+                path.display(), version, CURRENT_VERSION
+            );
+            return Ok(config);
+        }
+
+        let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(format!(".v{}.bak", version));
+        let backup = path.with_file_name(backup_name);
+        fs::write(&backup, &config_str)
+            .map_err(|source| ConfigError::Write { path: backup.clone(), source })?;
+        config.save(path)?;
+        log::info!(
+            "Upgraded {} to config version {}, original saved as {}",
+            path.display(), CURRENT_VERSION, backup.display()
+        );
         Ok(config)
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+    // Возвращает вместе с результатом разбора исходный текст файла.
+    fn read(path: &Path) -> Result<(Parsed, String), ConfigError> {
+        let format = ConfigFormat::from_path(path)?;
+        let config_str = fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        
+        let parsed = format.parse(&config_str)?;
+        parsed.config.validate()?;
+        Ok((parsed, config_str))
+    }
+
+    /// Сохраняет конфигурацию в формате, соответствующем расширению `path`.
+    /// Комментарии исходного файла при этом не сохраняются.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let format = ConfigFormat::from_path(path)?;
         self.validate()?;
-        let config_str = serde_json::to_string_pretty(self)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        let config_str = format.serialize(self)?;
         
-        fs::write("config.json", config_str)
-            .map_err(|e| format!("Failed to write config file: {}", e))?;
+        let write_error = |source| ConfigError::Write { path: path.to_path_buf(), source };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        fs::write(path, config_str)
+            .map_err(write_error)?;
         
         Ok(())
     }
 
-    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.inactivity_timeout == 0 {
-            return Err("inactivity_timeout must be greater than 0".into());
+    pub fn inactivity_timeout_for(&self, device: &DeviceConfig) -> u64 {
+        device.inactivity_timeout.unwrap_or(self.inactivity_timeout)
+    }
+
+    pub fn auto_connect_for(&self, device: &DeviceConfig) -> bool {
+        device.auto_connect.unwrap_or(self.auto_connect)
+    }
+
+    pub fn activity_policy(&self) -> ActivityPolicy {
+        ActivityPolicy {
+            peak_threshold: self.peak_threshold,
+            filter: SessionFilter::new(self.ignored_apps.clone(), self.trigger_apps.clone()),
         }
+    }
+
+    /// Проверяет конфигурацию целиком и сообщает обо всех проблемах сразу.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
 
-        if !self.device_address.contains(':') || self.device_address.len() != 17 {
-            return Err("Invalid device address format".into());
+        check_timeout(&mut errors, "inactivity_timeout", self.inactivity_timeout, 1, MAX_INACTIVITY_TIMEOUT);
+        check_timeout(&mut errors, "microphone_debounce", self.microphone_debounce, 0, MAX_MICROPHONE_DEBOUNCE);
+        check_timeout(&mut errors, "drain_timeout", self.drain_timeout, 0, MAX_DRAIN_TIMEOUT);
+        check_retry(&mut errors, "retry", &self.retry);
+
+        if self.devices.is_empty() {
+            errors.push(ValidationError::new("devices", "at least one device must be configured"));
+        }
+        // Неразборные адреса отсеивает ещё ConfigFormat::parse.
+        for (i, device) in self.devices.iter().enumerate() {
+            let field = format!("devices[{}]", i);
+            if let Some(first) = self.devices[..i].iter().position(|other| other.address == device.address) {
+                errors.push(ValidationError::new(
+                    format!("{}.address", field),
+                    format!("{} is already configured in devices[{}]", device.address, first),
+                ));
+            }
+            if device.profiles.is_empty() {
+                errors.push(ValidationError::new(format!("{}.profiles", field), "at least one profile must be enabled"));
+            }
+            if let Some(timeout) = device.inactivity_timeout {
+                let field = format!("{}.inactivity_timeout", field);
+                check_timeout(&mut errors, &field, timeout, 1, MAX_INACTIVITY_TIMEOUT);
+            }
+            if let Some(retry) = &device.retry {
+                check_retry(&mut errors, &format!("{}.retry", field), retry);
+            }
         }
 
-        Ok(())
+        if let Some(threshold) = self.peak_threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                errors.push(ValidationError::new("peak_threshold", "must be between 0.0 and 1.0"));
+            }
+        }
+
+        for (name, patterns) in [("ignored_apps", &self.ignored_apps), ("trigger_apps", &self.trigger_apps)] {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if pattern.trim().is_empty() {
+                    errors.push(ValidationError::new(format!("{}[{}]", name, i), "must not be empty"));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(errors))
+        }
+    }
+}
+
+fn check_timeout(errors: &mut Vec<ValidationError>, field: &str, value: u64, min: u64, max: u64) {
+    if !(min..=max).contains(&value) {
+        errors.push(ValidationError::new(field, format!("must be between {} and {} seconds", min, max)));
+    }
+}
+
+fn check_retry(errors: &mut Vec<ValidationError>, field: &str, retry: &RetryPolicy) {
+    for (name, message) in retry.validate() {
+        errors.push(ValidationError::new(format!("{}.{}", field, name), message));
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            inactivity_timeout: 300,
+            version: CURRENT_VERSION,
+            inactivity_timeout: default_inactivity_timeout(),
             auto_connect: true,
-            device_address: String::from("XX:XX:XX:XX:XX:XX"),
+            devices: Vec::new(),
+            peak_threshold: None,
+            ignored_apps: Vec::new(),
+            trigger_apps: Vec::new(),
+            microphone_debounce: default_microphone_debounce(),
+            retry: RetryPolicy::default(),
+            disconnect_on_stop: false,
+            drain_timeout: default_drain_timeout(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn config_changes_are_matched_by_file_name() {
+        let config = Path::new("/etc/btmnr/config.json");
+        let other = PathBuf::from("/etc/btmnr/other.json");
+
+        assert!(is_config_change(&DebouncedEvent::Create(config.to_path_buf()), config));
+        assert!(is_config_change(&DebouncedEvent::Write(config.to_path_buf()), config));
+        assert!(is_config_change(&DebouncedEvent::Rename(other.clone(), config.to_path_buf()), config));
+        assert!(!is_config_change(&DebouncedEvent::Write(other.clone()), config));
+        assert!(!is_config_change(&DebouncedEvent::Rename(config.to_path_buf(), other), config));
+        assert!(!is_config_change(&DebouncedEvent::NoticeWrite(config.to_path_buf()), config));
+    }
+
+    #[test]
+    fn bad_address_is_reported_with_its_path_alongside_other_errors() {
+        let text = r#"{
+            "version": 1,
+            "peak_threshold": 2.0,
+            "devices": [
+                { "address": "00:11:22:33:44:55" },
+                { "address": "00:11:22:33:44" },
+                { "address": 7 }
+            ]
+        }"#;
+
+        let errors = match ConfigFormat::Json.parse(text) {
+            Err(ConfigError::Validation(errors)) => errors,
+            other => panic!("expected validation errors, got {:?}", other.map(|parsed| parsed.version)),
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["devices[1].address", "devices[2].address", "peak_threshold"]);
+        assert!(errors[0].message.contains("expected 6 octets"), "{}", errors[0].message);
+    }
+
+    fn addresses(config: &Config) -> Vec<String> {
+        config.devices.iter().map(|device| device.address.to_string()).collect()
+    }
+
+    #[test]
+    fn v0_single_device_becomes_the_device_list() {
+        let Parsed { config, version, migrated } = ConfigFormat::Json.parse(include_str!("fixtures/v0.json")).unwrap();
+        assert_eq!(version, 0);
+        assert!(migrated);
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.inactivity_timeout, 120);
+        assert!(!config.auto_connect);
+        assert_eq!(addresses(&config), ["00:11:22:33:44:55"]);
+    }
+
+    #[test]
+    fn v0_device_address_is_placed_before_listed_devices() {
+        let Parsed { config, .. } = ConfigFormat::Json.parse(include_str!("fixtures/v0_with_devices.json")).unwrap();
+        assert_eq!(addresses(&config), ["00:11:22:33:44:55", "66:77:88:99:AA:BB"]);
+        assert_eq!(config.devices[1].name.as_deref(), Some("Speaker"));
+        assert_eq!(config.devices[1].priority, 5);
+    }
+
+    #[test]
+    fn v0_device_address_already_listed_is_not_duplicated() {
+        let Parsed { config, .. } = ConfigFormat::Json.parse(include_str!("fixtures/v0_duplicate_address.json")).unwrap();
+        assert_eq!(addresses(&config), ["00:11:22:33:44:55", "AA:BB:CC:DD:EE:FF"]);
+        assert_eq!(config.devices[1].name.as_deref(), Some("Headset"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        match ConfigFormat::Json.parse(include_str!("fixtures/v2.json")) {
+            Err(ConfigError::UnsupportedVersion { found, supported }) => {
+                assert_eq!(found, 2);
+                assert_eq!(supported, CURRENT_VERSION);
+            }
+            other => panic!("expected UnsupportedVersion, got {:?}", other.map(|parsed| parsed.version)),
+        }
+    }
+
+    #[test]
+    fn upgrade_keeps_the_original_and_rewrites_the_current_version() {
+        let dir = TempDir::new("upgrade");
+        let path = dir.join("config.json");
+        let original = include_str!("fixtures/v0_with_devices.json");
+        fs::write(&path, original).unwrap();
+
+        let config = Config::upgrade(&path).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("config.json.v0.bak")).unwrap(), original);
+
+        let rewritten: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(rewritten["version"], CURRENT_VERSION);
+        assert!(rewritten.get("device_address").is_none());
+        assert_eq!(Config::load(&path).unwrap().devices, config.devices);
+
+        // Файл текущей версии больше не трогается.
+        fs::remove_file(dir.join("config.json.v0.bak")).unwrap();
+        Config::upgrade(&path).unwrap();
+        assert!(!dir.join("config.json.v0.bak").exists());
+    }
+
+    #[test]
+    fn unversioned_file_is_rewritten_only_when_migrations_change_it() {
+        let dir = TempDir::new("upgrade");
+        let path = dir.join("config.json");
+        let original = r#"{ "devices": [{ "address": "00:11:22:33:44:55" }] }"#;
+        fs::write(&path, original).unwrap();
+
+        let config = Config::upgrade(&path).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert!(!dir.join("config.json.v0.bak").exists());
+    }
+
+    #[test]
+    fn toml_and_yaml_are_upgraded_in_memory_only() {
+        let dir = TempDir::new("upgrade");
+        let toml = "# Наушники\ndevice_address = \"00:11:22:33:44:55\"\n";
+        let yaml = "# Наушники\ndevice_address: '00:11:22:33:44:55'\n";
+        for (name, original) in [("config.toml", toml), ("config.yaml", yaml)] {
+            let path = dir.join(name);
+            fs::write(&path, original).unwrap();
+
+            let config = Config::upgrade(&path).unwrap();
+            assert_eq!(config.devices.len(), 1, "{}", name);
+            assert_eq!(fs::read_to_string(&path).unwrap(), original);
+            assert!(!dir.join(&format!("{}.v0.bak", name)).exists());
+        }
+    }
+
+    #[test]
+    fn missing_version_means_version_zero_everywhere() {
+        let config: Config = ConfigFormat::Json.deserialize("{}").unwrap();
+        assert_eq!(config.version, 0);
+        assert_eq!(migration::version(&serde_json::json!({})).unwrap(), 0);
+    }
+
+    fn sample_config() -> Config {
+        let mut headset = DeviceConfig::new("00:11:22:33:44:55".parse().unwrap());
+        headset.name = Some(String::from("Headset"));
+        headset.call_profiles = vec![Profile::HandsFree];
+        headset.retry = Some(RetryPolicy { max_attempts: 2, ..RetryPolicy::default() });
+        let mut speaker = DeviceConfig::new("66:77:88:99:AA:BB".parse().unwrap());
+        speaker.priority = 5;
+        speaker.inactivity_timeout = Some(30);
+        Config {
+            peak_threshold: Some(0.25),
+            ignored_apps: vec![String::from("zoom*")],
+            disconnect_on_stop: true,
+            drain_timeout: 7,
+            devices: vec![headset, speaker],
+            ..Config::default()
+        }
+    }
+
+    fn load_errors(path: &Path) -> Vec<ValidationError> {
+        match Config::load(path) {
+            Err(ConfigError::Validation(errors)) => errors,
+            other => panic!("expected validation errors for {}, got {:?}", path.display(), other.err()),
+        }
+    }
+
+    #[test]
+    fn config_survives_json_toml_and_yaml() {
+        let original = sample_config();
+        let mut config = original.clone();
+        for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+            let text = format.serialize(&config).unwrap();
+            let parsed = format.parse(&text).unwrap();
+            assert_eq!(parsed.version, CURRENT_VERSION, "{}", format);
+            config = parsed.config;
+        }
+        assert_eq!(serde_json::to_value(&config).unwrap(), serde_json::to_value(&original).unwrap());
+    }
+
+    #[test]
+    fn invalid_files_report_the_same_errors_in_every_format() {
+        let json = r#"{
+            "version": 1,
+            "inactivity_timeout": 0,
+            "peak_threshold": 1.5,
+            "ignored_apps": [" "],
+            "devices": [
+                { "address": "00:11:22:33:44:55" },
+                { "address": "00-11-22-33-44-55", "profiles": [] }
+            ]
+        }"#;
+        let toml = r#"
+            version = 1
+            inactivity_timeout = 0
+            peak_threshold = 1.5
+            ignored_apps = [" "]
+
+            [[devices]]
+            address = "00:11:22:33:44:55"
+
+            [[devices]]
+            address = "00-11-22-33-44-55"
+            profiles = []
+        "#;
+        let yaml = "
+version: 1
+inactivity_timeout: 0
+peak_threshold: 1.5
+ignored_apps: [' ']
+devices:
+  - address: '00:11:22:33:44:55'
+  - address: '00-11-22-33-44-55'
+    profiles: []
+";
+        let dir = TempDir::new("formats");
+        let mut results = Vec::new();
+        for (name, text) in [("config.json", json), ("config.toml", toml), ("config.yaml", yaml)] {
+            fs::write(dir.join(name), text).unwrap();
+            results.push(load_errors(&dir.join(name)));
+        }
+
+        let fields: Vec<&str> = results[0].iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["inactivity_timeout", "devices[1].address", "devices[1].profiles", "peak_threshold", "ignored_apps[0]"]
+        );
+        assert_eq!(results[1], results[0]);
+        assert_eq!(results[2], results[0]);
+    }
+
+    fn error_fields(config: &Config) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Validation(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn timeouts_are_checked_against_their_bounds() {
+        let valid = sample_config();
+        assert!(error_fields(&valid).is_empty());
+
+        let edges = Config { inactivity_timeout: 1, microphone_debounce: 0, drain_timeout: 0, ..valid.clone() };
+        assert!(error_fields(&edges).is_empty());
+        let edges = Config {
+            inactivity_timeout: MAX_INACTIVITY_TIMEOUT,
+            microphone_debounce: MAX_MICROPHONE_DEBOUNCE,
+            drain_timeout: MAX_DRAIN_TIMEOUT,
+            ..valid.clone()
+        };
+        assert!(error_fields(&edges).is_empty());
+
+        let mut config = Config {
+            inactivity_timeout: 0,
+            microphone_debounce: MAX_MICROPHONE_DEBOUNCE + 1,
+            drain_timeout: MAX_DRAIN_TIMEOUT + 1,
+            ..valid
+        };
+        config.devices[1].inactivity_timeout = Some(MAX_INACTIVITY_TIMEOUT + 1);
+        assert_eq!(
+            error_fields(&config),
+            ["inactivity_timeout", "microphone_debounce", "drain_timeout", "devices[1].inactivity_timeout"]
+        );
+    }
+
+    #[test]
+    fn retry_policies_are_checked_with_their_path() {
+        let mut config = sample_config();
+        config.retry = RetryPolicy { initial_delay: 0.0, multiplier: 0.5, jitter: 1.5, ..RetryPolicy::default() };
+        config.devices[0].retry = Some(RetryPolicy { initial_delay: 10.0, max_delay: 5.0, ..RetryPolicy::default() });
+
+        assert_eq!(
+            error_fields(&config),
+            ["retry.initial_delay", "retry.multiplier", "retry.jitter", "devices[0].retry.max_delay"]
+        );
+        config.devices[0].retry = Some(RetryPolicy { max_delay: f64::NAN, ..RetryPolicy::default() });
+        assert_eq!(error_fields(&config).last().map(String::as_str), Some("devices[0].retry.max_delay"));
+    }
+
+    #[test]
+    fn peak_threshold_and_patterns_are_checked() {
+        for threshold in [0.0, 1.0] {
+            assert!(error_fields(&Config { peak_threshold: Some(threshold), ..sample_config() }).is_empty());
+        }
+        for threshold in [-0.1, 1.1, f32::NAN] {
+            assert_eq!(error_fields(&Config { peak_threshold: Some(threshold), ..sample_config() }), ["peak_threshold"]);
+        }
+
+        let config = Config {
+            ignored_apps: vec![String::from("zoom*"), String::new()],
+            trigger_apps: vec![String::from("  ")],
+            ..sample_config()
+        };
+        assert_eq!(error_fields(&config), ["ignored_apps[1]", "trigger_apps[0]"]);
+    }
+
+    #[test]
+    fn devices_must_be_present_unique_and_have_profiles() {
+        assert_eq!(error_fields(&Config { devices: Vec::new(), ..sample_config() }), ["devices"]);
+
+        let mut config = sample_config();
+        let mut duplicate = config.devices[0].clone();
+        duplicate.profiles.clear();
+        config.devices.push(duplicate);
+        let Err(ConfigError::Validation(errors)) = config.validate() else {
+            panic!("duplicate address was accepted");
+        };
+        assert_eq!(
+            errors,
+            [
+                ValidationError::new("devices[2].address", "00:11:22:33:44:55 is already configured in devices[0]"),
+                ValidationError::new("devices[2].profiles", "at least one profile must be enabled"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_v0_address_is_reported_where_the_file_has_it() {
+        let text = r#"{ "inactivity_timeout": 0, "device_address": "00:11:22:33:44" }"#;
+
+        let Err(ConfigError::Validation(errors)) = ConfigFormat::Json.parse(text) else {
+            panic!("invalid v0 address was accepted");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["device_address", "inactivity_timeout"]);
+    }
+
+    #[test]
+    fn watcher_picks_up_a_config_created_later() {
+        let dir = TempDir::new("watch");
+        let path = dir.join("config.json");
+        let manager = ConfigManager::new(path.clone());
+        assert_eq!(manager.get_config().inactivity_timeout, Config::default().inactivity_timeout);
+
+        save_and_wait_for_reload(&manager, &path);
+    }
+
+    #[test]
+    fn watcher_picks_up_a_config_in_a_directory_created_on_start() {
+        let dir = TempDir::new("watch");
+        let path = dir.join("btmnr").join("config.json");
+        let manager = ConfigManager::new(path.clone());
+        assert!(dir.join("btmnr").is_dir());
+
+        save_and_wait_for_reload(&manager, &path);
+    }
+
+    fn save_and_wait_for_reload(manager: &ConfigManager, path: &Path) {
+        let config = Config {
+            inactivity_timeout: 42,
+            devices: vec![DeviceConfig::new("00:11:22:33:44:55".parse().unwrap())],
+            ..Config::default()
+        };
+        config.save(path).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while manager.get_config().inactivity_timeout != 42 {
+            assert!(Instant::now() < deadline, "configuration was not reloaded");
+            std::thread::sleep(Duration::from_millis(50));
         }
     }
 }
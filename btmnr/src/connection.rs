@@ -0,0 +1,324 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::retry::{Jitter, RetryPolicy};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Connect,
+    Disconnect,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    AudioActive,
+    AudioIdle,
+    LinkUp,
+    LinkDown,
+    /// Постоянная ошибка (нет сопряжения, нет прав) сразу исчерпывает
+    /// попытки эпизода: повтор её не исправит.
+    LinkFailed { transient: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    IdleGrace { since: Instant },
+    Disconnecting,
+    // `retry_at == None` — попытки в этом эпизоде исчерпаны.
+    Failed { retry: Action, attempts: u32, retry_at: Option<Instant> },
+}
+
+impl ConnectionState {
+    // Устройство уже занято (подключено или в процессе), и выбирать
+    // другое при появлении звука не нужно.
+    pub fn is_engaged(&self) -> bool {
+        !matches!(
+            self,
+            ConnectionState::Disconnected | ConnectionState::Failed { retry: Action::Connect, .. }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionPolicy {
+    pub inactivity_timeout: Duration,
+    pub auto_connect: bool,
+    pub retry: RetryPolicy,
+}
+
+/// Конечный автомат соединения с одной гарнитурой. Не выполняет никаких
+/// вызовов сам: на каждое событие возвращает действие, которое нужно
+/// выполнить, а результат действия подаётся обратно событием
+/// `LinkUp`/`LinkDown`/`LinkFailed`.
+#[derive(Clone, Debug)]
+pub struct ConnectionMachine {
+    state: ConnectionState,
+    failures: u32,
+    jitter: Jitter,
+}
+
+impl ConnectionMachine {
+    pub fn new(state: ConnectionState) -> Self {
+        Self::with_jitter(state, Jitter::new())
+    }
+
+    pub fn with_jitter(state: ConnectionState, jitter: Jitter) -> Self {
+        Self { state, failures: 0, jitter }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn handle(&mut self, event: Event, now: Instant, policy: &ConnectionPolicy) -> Option<Action> {
+        use ConnectionState::*;
+
+        let (next, action) = match (self.state, event) {
+            (_, Event::LinkDown) => (Disconnected, None),
+            (_, Event::LinkUp) => (Connected, None),
+
+            (Disconnected, Event::AudioActive) if policy.auto_connect => (Connecting, Some(Action::Connect)),
+            (Connecting, Event::LinkFailed { transient }) => {
+                (self.fail(Action::Connect, transient, now, &policy.retry), None)
+            }
+
+            (Connected, Event::AudioIdle) => (IdleGrace { since: now }, None),
+
+            (IdleGrace { .. }, Event::AudioActive) => (Connected, None),
+            (IdleGrace { since }, Event::AudioIdle)
+                if now.duration_since(since) >= policy.inactivity_timeout =>
+            {
+                (Disconnecting, Some(Action::Disconnect))
+            }
+
+            (Disconnecting, Event::LinkFailed { transient }) => {
+                (self.fail(Action::Disconnect, transient, now, &policy.retry), None)
+            }
+
+            // Эпизод закончился сам собой: звук пропал до успешного
+            // подключения или вернулся до успешного отключения.
+            (Failed { retry: Action::Connect, .. }, Event::AudioIdle) => (Disconnected, None),
+            (Failed { retry: Action::Disconnect, .. }, Event::AudioActive) => (Connected, None),
+            (Failed { retry, retry_at: Some(retry_at), .. }, event) if now >= retry_at => {
+                match (retry, event) {
+                    (Action::Connect, Event::AudioActive) if policy.auto_connect => {
+                        (Connecting, Some(Action::Connect))
+                    }
+                    (Action::Disconnect, Event::AudioIdle) => (Disconnecting, Some(Action::Disconnect)),
+                    _ => (self.state, None),
+                }
+            }
+
+            (state, _) => (state, None),
+        };
+
+        if !matches!(next, Connecting | Disconnecting | Failed { .. }) {
+            self.failures = 0;
+        }
+        if next != self.state {
+            log::debug!("Connection state {:?} -> {:?} on {:?}", self.state, next, event);
+        }
+        self.state = next;
+        action
+    }
+
+    fn fail(&mut self, retry: Action, transient: bool, now: Instant, policy: &RetryPolicy) -> ConnectionState {
+        self.failures += 1;
+        let retry_at = if !transient || policy.exhausted(self.failures) {
+            None
+        } else {
+            Some(now + policy.delay(self.failures, self.jitter.sample()))
+        };
+        ConnectionState::Failed { retry, attempts: self.failures, retry_at }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    fn policy() -> ConnectionPolicy {
+        ConnectionPolicy {
+            inactivity_timeout: TIMEOUT,
+            auto_connect: true,
+            retry: RetryPolicy { jitter: 0.0, ..RetryPolicy::default() },
+        }
+    }
+
+    fn machine() -> ConnectionMachine {
+        ConnectionMachine::with_jitter(ConnectionState::Disconnected, Jitter::with_seed(7))
+    }
+
+    #[test]
+    fn connects_on_audio_and_disconnects_after_grace_period() {
+        let (mut machine, policy, start) = (machine(), policy(), Instant::now());
+
+        assert_eq!(machine.handle(Event::AudioActive, start, &policy), Some(Action::Connect));
+        assert_eq!(machine.state(), ConnectionState::Connecting);
+        assert_eq!(machine.handle(Event::LinkUp, start, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Connected);
+
+        let idle = start + Duration::from_secs(5);
+        assert_eq!(machine.handle(Event::AudioIdle, idle, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::IdleGrace { since: idle });
+        assert_eq!(machine.handle(Event::AudioIdle, idle + TIMEOUT - Duration::from_millis(1), &policy), None);
+        assert_eq!(machine.handle(Event::AudioIdle, idle + TIMEOUT, &policy), Some(Action::Disconnect));
+        assert_eq!(machine.state(), ConnectionState::Disconnecting);
+
+        assert_eq!(machine.handle(Event::LinkDown, idle + TIMEOUT, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Disconnected);
+    }
+
+    #[test]
+    fn audio_during_grace_period_keeps_the_link() {
+        let (mut machine, policy, start) = (machine(), policy(), Instant::now());
+        machine.handle(Event::LinkUp, start, &policy);
+        machine.handle(Event::AudioIdle, start, &policy);
+
+        assert_eq!(machine.handle(Event::AudioActive, start + Duration::from_secs(30), &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Connected);
+        // Отсчёт тишины начинается заново.
+        assert_eq!(machine.handle(Event::AudioIdle, start + TIMEOUT, &policy), None);
+    }
+
+    #[test]
+    fn does_not_connect_without_auto_connect() {
+        let policy = ConnectionPolicy { auto_connect: false, ..policy() };
+        let mut machine = machine();
+
+        assert_eq!(machine.handle(Event::AudioActive, Instant::now(), &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Disconnected);
+        assert!(!machine.state().is_engaged());
+    }
+
+    #[test]
+    fn failed_connect_waits_for_retry_time() {
+        let (mut machine, policy, start) = (machine(), policy(), Instant::now());
+        machine.handle(Event::AudioActive, start, &policy);
+        machine.handle(Event::LinkFailed { transient: true }, start, &policy);
+
+        let retry_at = start + Duration::from_secs(2);
+        assert_eq!(
+            machine.state(),
+            ConnectionState::Failed { retry: Action::Connect, attempts: 1, retry_at: Some(retry_at) }
+        );
+        assert!(!machine.state().is_engaged());
+        assert_eq!(machine.handle(Event::AudioActive, retry_at - Duration::from_millis(1), &policy), None);
+        assert_eq!(machine.handle(Event::AudioActive, retry_at, &policy), Some(Action::Connect));
+    }
+
+    #[test]
+    fn episode_ends_when_audio_changes_its_mind() {
+        let (mut machine, policy, start) = (machine(), policy(), Instant::now());
+        machine.handle(Event::AudioActive, start, &policy);
+        machine.handle(Event::LinkFailed { transient: true }, start, &policy);
+        assert_eq!(machine.handle(Event::AudioIdle, start, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Disconnected);
+
+        machine.handle(Event::LinkUp, start, &policy);
+        machine.handle(Event::AudioIdle, start, &policy);
+        machine.handle(Event::AudioIdle, start + TIMEOUT, &policy);
+        machine.handle(Event::LinkFailed { transient: true }, start + TIMEOUT, &policy);
+        assert!(matches!(machine.state(), ConnectionState::Failed { retry: Action::Disconnect, .. }));
+        assert!(machine.state().is_engaged());
+        assert_eq!(machine.handle(Event::AudioActive, start + TIMEOUT, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Connected);
+    }
+
+    fn retry_delays(machine: &mut ConnectionMachine, policy: &ConnectionPolicy, start: Instant) -> Vec<Option<u64>> {
+        let mut delays = Vec::new();
+        let mut now = start;
+        machine.handle(Event::AudioActive, now, policy);
+        loop {
+            machine.handle(Event::LinkFailed { transient: true }, now, policy);
+            let ConnectionState::Failed { retry_at, .. } = machine.state() else {
+                panic!("unexpected state {:?}", machine.state());
+            };
+            delays.push(retry_at.map(|retry_at| (retry_at - now).as_secs()));
+            match retry_at {
+                Some(retry_at) => now = retry_at,
+                None => return delays,
+            }
+            assert_eq!(machine.handle(Event::AudioActive, now, policy), Some(Action::Connect));
+        }
+    }
+
+    #[test]
+    fn retry_delay_grows_and_is_clamped() {
+        let retry = RetryPolicy { initial_delay: 2.0, multiplier: 3.0, max_delay: 30.0, jitter: 0.0, max_attempts: 6 };
+        let policy = ConnectionPolicy { retry, ..policy() };
+
+        let delays = retry_delays(&mut machine(), &policy, Instant::now());
+        assert_eq!(delays, [Some(2), Some(6), Some(18), Some(30), Some(30), None]);
+    }
+
+    #[test]
+    fn exhausted_attempts_wait_for_audio_to_change() {
+        let retry = RetryPolicy { max_attempts: 2, jitter: 0.0, ..RetryPolicy::default() };
+        let (mut machine, policy, start) = (machine(), ConnectionPolicy { retry, ..policy() }, Instant::now());
+
+        assert_eq!(retry_delays(&mut machine, &policy, start), [Some(2), None]);
+        assert_eq!(
+            machine.state(),
+            ConnectionState::Failed { retry: Action::Connect, attempts: 2, retry_at: None }
+        );
+        // Сколько бы ни длился звук, новых попыток в этом эпизоде нет.
+        assert_eq!(machine.handle(Event::AudioActive, start + Duration::from_secs(3600), &policy), None);
+    }
+
+    #[test]
+    fn permanent_failure_gives_up_at_once() {
+        let (mut machine, policy, start) = (machine(), policy(), Instant::now());
+        machine.handle(Event::AudioActive, start, &policy);
+        machine.handle(Event::LinkFailed { transient: false }, start, &policy);
+
+        assert_eq!(
+            machine.state(),
+            ConnectionState::Failed { retry: Action::Connect, attempts: 1, retry_at: None }
+        );
+        assert_eq!(machine.handle(Event::AudioActive, start + Duration::from_secs(3600), &policy), None);
+    }
+
+    #[test]
+    fn audio_idle_starts_a_new_episode() {
+        let retry = RetryPolicy { max_attempts: 2, jitter: 0.0, ..RetryPolicy::default() };
+        let (mut machine, policy, start) = (machine(), ConnectionPolicy { retry, ..policy() }, Instant::now());
+        retry_delays(&mut machine, &policy, start);
+
+        assert_eq!(machine.handle(Event::AudioIdle, start, &policy), None);
+        assert_eq!(machine.state(), ConnectionState::Disconnected);
+        assert_eq!(machine.handle(Event::AudioActive, start, &policy), Some(Action::Connect));
+        machine.handle(Event::LinkFailed { transient: true }, start, &policy);
+        assert_eq!(
+            machine.state(),
+            ConnectionState::Failed {
+                retry: Action::Connect,
+                attempts: 1,
+                retry_at: Some(start + Duration::from_secs(2)),
+            }
+        );
+    }
+
+    #[test]
+    fn seeded_jitter_is_reproducible() {
+        let policy = ConnectionPolicy { retry: RetryPolicy { jitter: 0.5, ..RetryPolicy::default() }, ..policy() };
+        let start = Instant::now();
+        let retry_at = |seed| {
+            let mut machine = ConnectionMachine::with_jitter(ConnectionState::Connecting, Jitter::with_seed(seed));
+            machine.handle(Event::LinkFailed { transient: true }, start, &policy);
+            match machine.state() {
+                ConnectionState::Failed { retry_at: Some(retry_at), .. } => retry_at - start,
+                state => panic!("unexpected state {:?}", state),
+            }
+        };
+
+        assert_eq!(retry_at(0xDEAD_BEEF), retry_at(0xDEAD_BEEF));
+        assert_ne!(retry_at(0xDEAD_BEEF), retry_at(0x1234_5678_9ABC_DEF0));
+        assert!((Duration::from_secs(1)..=Duration::from_secs(3)).contains(&retry_at(0xDEAD_BEEF)));
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Команды, которые можно отправить работающему сервису: через SCM на
+/// Windows или через локальный сокет на других платформах. Все они идут
+/// в `BluetoothManager` по одному каналу.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManagerCommand {
+    // Приостанавливает автоматическое подключение и отключение.
+    Pause,
+    Resume,
+    ConnectNow,
+    DisconnectNow,
+    ReloadConfig,
+}
+
+// Пользовательские коды управления службой должны лежать в 128..=255.
+#[cfg(windows)]
+const CONNECT_NOW_CODE: u32 = 128;
+#[cfg(windows)]
+const DISCONNECT_NOW_CODE: u32 = 129;
+#[cfg(windows)]
+const RELOAD_CONFIG_CODE: u32 = 130;
+
+impl ManagerCommand {
+    pub const ALL: [ManagerCommand; 5] = [
+        ManagerCommand::Pause,
+        ManagerCommand::Resume,
+        ManagerCommand::ConnectNow,
+        ManagerCommand::DisconnectNow,
+        ManagerCommand::ReloadConfig,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManagerCommand::Pause => "pause",
+            ManagerCommand::Resume => "resume",
+            ManagerCommand::ConnectNow => "connect-now",
+            ManagerCommand::DisconnectNow => "disconnect-now",
+            ManagerCommand::ReloadConfig => "reload-config",
+        }
+    }
+
+    /// Код для `ServiceControl::UserEvent`. Pause и Resume передаются
+    /// штатными кодами SCM и своего кода не имеют.
+    #[cfg(windows)]
+    pub fn control_code(&self) -> Option<u32> {
+        match self {
+            ManagerCommand::ConnectNow => Some(CONNECT_NOW_CODE),
+            ManagerCommand::DisconnectNow => Some(DISCONNECT_NOW_CODE),
+            ManagerCommand::ReloadConfig => Some(RELOAD_CONFIG_CODE),
+            ManagerCommand::Pause | ManagerCommand::Resume => None,
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn from_control_code(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.control_code() == Some(code))
+    }
+}
+
+impl fmt::Display for ManagerCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ManagerCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|command| command.as_str() == s)
+            .ok_or_else(|| format!("Unknown command: {}", s))
+    }
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use crate::audio::AudioError;
+use crate::bluetooth::BluetoothError;
+use crate::config::ConfigError;
+
+/// Общая ошибка сервиса. Различает временные сбои, после которых имеет
+/// смысл повторить попытку, и постоянные, которые повтором не исправить.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Audio error: {0}")]
+    Audio(#[from] AudioError),
+    #[error("Bluetooth error: {0}")]
+    Bluetooth(#[from] BluetoothError),
+    #[error("Configuration error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(windows)]
+    #[error("Service error: {0}")]
+    Service(#[from] windows_service::Error),
+    #[error("Installation failed: {0}")]
+    Install(String),
+    #[error("Service rejected the command: {0}")]
+    Control(String),
+    #[error("The {0} command is not supported on this platform")]
+    Unsupported(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Audio(e) => e.is_transient(),
+            Error::Bluetooth(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+}
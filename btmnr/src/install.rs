@@ -0,0 +1,276 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::Error;
+
+pub const SERVICE_NAME: &str = "BluetoothManager";
+pub const UNIT_NAME: &str = "btmnr.service";
+
+/// Описание службы, не зависящее от платформы. Из него строятся и запись
+/// в SCM, и unit-файл systemd.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceDefinition {
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub description: &'static str,
+    pub launch_arguments: Vec<String>,
+    // Службы Windows, без которых сервис бесполезен.
+    pub dependencies: Vec<&'static str>,
+    // Задержки перед перезапуском после первого, второго и последующих сбоев.
+    pub restart_delays: Vec<Duration>,
+    pub failure_reset_period: Duration,
+}
+
+/// Путь к конфигурации записывается в аргументы запуска, чтобы служба
+/// читала тот же файл, что был выбран при установке.
+pub fn service_definition(config_path: &Path) -> ServiceDefinition {
+    ServiceDefinition {
+        name: SERVICE_NAME,
+        display_name: "Bluetooth Headset Manager",
+        description: "Connects Bluetooth headsets while audio is playing and disconnects them when idle.",
+        launch_arguments: vec![
+            String::from("--config"),
+            config_path.to_string_lossy().into_owned(),
+            String::from("service"),
+        ],
+        dependencies: vec!["bthserv", "Audiosrv"],
+        restart_delays: vec![Duration::from_secs(5), Duration::from_secs(30), Duration::from_secs(60)],
+        failure_reset_period: Duration::from_secs(24 * 60 * 60),
+    }
+}
+
+/// Содержимое пользовательского unit-файла systemd для `executable`.
+pub fn systemd_unit(executable: &Path, config_path: &Path) -> String {
+    let definition = service_definition(config_path);
+    let restart_delay = definition.restart_delays.first().copied().unwrap_or_default();
+    format!(
+        "[Unit]\n\
+         Description={}\n\
+         After=pipewire-pulse.service pulseaudio.service\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={} --config {} run\n\
+         Restart=on-failure\n\
+         RestartSec={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        definition.display_name,
+        systemd_quote(&executable.to_string_lossy()),
+        systemd_quote(&config_path.to_string_lossy()),
+        restart_delay.as_secs(),
+    )
+}
+
+// Служба стартует не из того каталога, где запускали install: SCM —
+// из System32, systemd — из домашнего каталога.
+#[cfg(any(windows, target_os = "linux"))]
+fn absolute_config_path(config_path: &Path) -> Result<std::path::PathBuf, Error> {
+    if config_path.is_absolute() {
+        Ok(config_path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(config_path))
+    }
+}
+
+// systemd разбирает ExecStart сам: пути с пробелами нужно заключать
+// в кавычки, `%` удваивать, чтобы он не считался спецификатором, а `$` —
+// чтобы `$VAR` и `${VAR}` не подставлялись из окружения.
+fn systemd_quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    if escaped.chars().any(char::is_whitespace) || escaped.contains('"') {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(windows)]
+pub fn install(config_path: &Path) -> Result<(), Error> {
+    use std::ffi::OsString;
+    use windows_service::{
+        service::{
+            ServiceAccess, ServiceAction, ServiceActionType, ServiceDependency, ServiceErrorControl,
+            ServiceFailureActions, ServiceFailureResetPeriod, ServiceInfo, ServiceStartType, ServiceType,
+        },
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+
+    let definition = service_definition(&absolute_config_path(config_path)?);
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+
+    let info = ServiceInfo {
+        name: OsString::from(definition.name),
+        display_name: OsString::from(definition.display_name),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: definition.launch_arguments.iter().map(OsString::from).collect(),
+        dependencies: definition
+            .dependencies
+            .iter()
+            .map(|name| ServiceDependency::Service(OsString::from(name)))
+            .collect(),
+        account_name: None,
+        account_password: None,
+    };
+    // Действие Restart в failure actions SCM принимает только с правом START.
+    let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)?;
+    service.set_description(definition.description)?;
+
+    let actions = definition
+        .restart_delays
+        .iter()
+        .map(|&delay| ServiceAction { action_type: ServiceActionType::Restart, delay })
+        .collect();
+    service.update_failure_actions(ServiceFailureActions {
+        reset_period: ServiceFailureResetPeriod::After(definition.failure_reset_period),
+        reboot_msg: None,
+        command: None,
+        actions: Some(actions),
+    })?;
+    service.set_failure_actions_on_non_crash_failures(true)?;
+
+    println!("Installed service {}", definition.name);
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn uninstall() -> Result<(), Error> {
+    use windows_service::{
+        service::{ServiceAccess, ServiceState},
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        SERVICE_NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+    // SCM удалит запись, как только закроются все открытые дескрипторы.
+    service.delete()?;
+
+    println!("Uninstalled service {}", SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn install(config_path: &Path) -> Result<(), Error> {
+    let unit_path = systemd_unit_path()?;
+    if let Some(dir) = unit_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let unit = systemd_unit(&std::env::current_exe()?, &absolute_config_path(config_path)?);
+    std::fs::write(&unit_path, unit)?;
+
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", UNIT_NAME])?;
+    println!("Installed {}", unit_path.display());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn uninstall() -> Result<(), Error> {
+    let unit_path = systemd_unit_path()?;
+    if !unit_path.exists() {
+        return Err(Error::Install(format!("{} is not installed", unit_path.display())));
+    }
+
+    systemctl(&["disable", "--now", UNIT_NAME])?;
+    std::fs::remove_file(&unit_path)?;
+    systemctl(&["daemon-reload"])?;
+    println!("Removed {}", unit_path.display());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn systemd_unit_path() -> Result<std::path::PathBuf, Error> {
+    let config_home = crate::paths::config_home()
+        .ok_or_else(|| Error::Install(String::from("Neither XDG_CONFIG_HOME nor HOME is set")))?;
+    Ok(config_home.join("systemd").join("user").join(UNIT_NAME))
+}
+
+#[cfg(target_os = "linux")]
+fn systemctl(args: &[&str]) -> Result<(), Error> {
+    let status = std::process::Command::new("systemctl").arg("--user").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Install(format!("systemctl --user {} failed with {}", args.join(" "), status)))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn install(_config_path: &Path) -> Result<(), Error> {
+    Err(Error::Unsupported("install"))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn uninstall() -> Result<(), Error> {
+    Err(Error::Unsupported("uninstall"))
+}
+
+// Пути в снимках записаны в формате Unix.
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_definition_passes_config_path() {
+        let definition = service_definition(Path::new("/etc/btmnr/config.toml"));
+
+        assert_eq!(definition.launch_arguments, ["--config", "/etc/btmnr/config.toml", "service"]);
+        assert_eq!(definition.dependencies, ["bthserv", "Audiosrv"]);
+        assert_eq!(
+            definition.restart_delays,
+            [Duration::from_secs(5), Duration::from_secs(30), Duration::from_secs(60)]
+        );
+    }
+
+    #[test]
+    fn systemd_unit_snapshot() {
+        let unit = systemd_unit(Path::new("/usr/local/bin/btmnr"), Path::new("/home/user/.config/btmnr/config.json"));
+
+        assert_eq!(
+            unit,
+            "[Unit]\n\
+             Description=Bluetooth Headset Manager\n\
+             After=pipewire-pulse.service pulseaudio.service\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart=/usr/local/bin/btmnr --config /home/user/.config/btmnr/config.json run\n\
+             Restart=on-failure\n\
+             RestartSec=5\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n"
+        );
+    }
+
+    #[test]
+    fn systemd_unit_quotes_spaces_and_specifiers() {
+        let unit = systemd_unit(Path::new("/opt/My Apps/btmnr"), Path::new("/home/user/100%/config.json"));
+        assert!(unit.contains("ExecStart=\"/opt/My Apps/btmnr\" --config /home/user/100%%/config.json run\n"));
+
+        let unit = systemd_unit(Path::new("/opt/$HOME/btmnr"), Path::new("/home/user/${USER} files/config.json"));
+        assert!(unit.contains("ExecStart=/opt/$$HOME/btmnr --config \"/home/user/$${USER} files/config.json\" run\n"));
+    }
+
+    #[test]
+    fn relative_config_path_is_resolved_against_current_dir() {
+        let resolved = absolute_config_path(Path::new("config.json")).unwrap();
+
+        assert!(resolved.is_absolute());
+        assert_eq!(resolved, std::env::current_dir().unwrap().join("config.json"));
+        assert_eq!(absolute_config_path(Path::new("/etc/btmnr.json")).unwrap(), Path::new("/etc/btmnr.json"));
+    }
+}
@@ -0,0 +1,280 @@
+use log::{error, info, warn};
+use std::fs::Permissions;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::watch;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+use crate::control::ManagerCommand;
+use crate::error::Error;
+use crate::manager::DeviceStatus;
+
+// Протокол: клиент пишет одну строку с именем команды, сервис отвечает
+// `ok` или `error: <причина>`. На запрос `status` сервис отвечает строкой
+// на каждое устройство и закрывает соединение.
+
+const STATUS_REQUEST: &str = "status";
+
+// Сколько ждать строку от клиента, прежде чем закрыть соединение.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Сокет лежит в XDG_RUNTIME_DIR, доступном только владельцу. Общий
+/// временный каталог для этого не годится: там сокет может подменить
+/// или занять другой пользователь.
+pub fn socket_path() -> io::Result<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join("btmnr.sock"))
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))
+}
+
+/// Принимает команды и запросы состояния на `path`, пока не отменён `cancel`.
+pub async fn serve(
+    path: &Path,
+    commands: UnboundedSender<ManagerCommand>,
+    status: watch::Receiver<Vec<DeviceStatus>>,
+    cancel: CancellationToken,
+) -> std::io::Result<()> {
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path)?;
+    // Права на сам сокет не должны зависеть от umask.
+    std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    info!("Listening for commands on {}", path.display());
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                // Каждый клиент обслуживается отдельно, чтобы молчащий
+                // клиент не задерживал остальных.
+                Ok((stream, _)) => {
+                    let commands = commands.clone();
+                    let status = status.clone();
+                    tokio::spawn(async move {
+                        match timeout(CLIENT_TIMEOUT, handle_client(stream, &commands, &status)).await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => warn!("Failed to serve command client: {}", e),
+                            Err(_) => warn!("Command client sent nothing for {}s, closing", CLIENT_TIMEOUT.as_secs()),
+                        }
+                    });
+                }
+                Err(e) => error!("Failed to accept command client: {}", e),
+            },
+        }
+    }
+
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+// Сокет мог остаться от предыдущего запуска, завершившегося аварийно.
+// Удаляется только такой сокет: чужой файл или сокет, который кто-то
+// ещё слушает, остаются на месте.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            ErrorKind::AddrInUse,
+            format!("another instance is already listening on {}", path.display()),
+        )),
+        Err(_) => std::fs::remove_file(path),
+    }
+}
+
+async fn handle_client(
+    stream: UnixStream,
+    commands: &UnboundedSender<ManagerCommand>,
+    status: &watch::Receiver<Vec<DeviceStatus>>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader).read_line(&mut line).await?;
+
+    let request = line.trim();
+    let reply = if request == STATUS_REQUEST {
+        status.borrow().iter().map(|device| format!("{}\n", device)).collect()
+    } else {
+        match request.parse::<ManagerCommand>() {
+            Ok(command) => match commands.send(command) {
+                Ok(()) => String::from("ok\n"),
+                Err(_) => String::from("error: service is stopping\n"),
+            },
+            Err(e) => format!("error: {}\n", e),
+        }
+    };
+    writer.write_all(reply.as_bytes()).await
+}
+
+pub fn send(path: &Path, command: ManagerCommand) -> Result<(), Error> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    writeln!(stream, "{}", command)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(Error::Control(other.trim_start_matches("error: ").to_string())),
+    }
+}
+
+/// Состояние устройств работающего сервиса, по строке на устройство.
+pub fn query_status(path: &Path) -> Result<Vec<String>, Error> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    writeln!(stream, "{}", STATUS_REQUEST)?;
+
+    let mut lines = Vec::new();
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if let Some(reason) = line.strip_prefix("error: ") {
+            return Err(Error::Control(reason.to_string()));
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+    use crate::connection::ConnectionState;
+    use crate::switching::AudioMode;
+    use crate::testing::TempDir;
+
+    fn start(path: PathBuf) -> (JoinHandle<io::Result<()>>, mpsc::UnboundedReceiver<ManagerCommand>, CancellationToken) {
+        start_with_status(path, watch::channel(Vec::new()).1)
+    }
+
+    fn start_with_status(
+        path: PathBuf,
+        status: watch::Receiver<Vec<DeviceStatus>>,
+    ) -> (JoinHandle<io::Result<()>>, mpsc::UnboundedReceiver<ManagerCommand>, CancellationToken) {
+        let (commands, received) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+        let server_cancel = cancel.clone();
+        let server = tokio::spawn(async move { serve(&path, commands, status, server_cancel).await });
+        (server, received, cancel)
+    }
+
+    async fn wait_for_socket(path: &Path) {
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    async fn send_async(path: &Path, command: ManagerCommand) -> Result<(), Error> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || send(&path, command)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn delivers_commands_and_cleans_up() {
+        let dir = TempDir::new("ipc");
+        let path = dir.join("btmnr.sock");
+        let (server, mut received, cancel) = start(path.clone());
+        wait_for_socket(&path).await;
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        send_async(&path, ManagerCommand::Pause).await.unwrap();
+        assert_eq!(received.recv().await, Some(ManagerCommand::Pause));
+
+        cancel.cancel();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn answers_status_queries() {
+        let dir = TempDir::new("ipc");
+        let path = dir.join("btmnr.sock");
+        let device = DeviceStatus {
+            address: "00:11:22:33:44:55".parse().unwrap(),
+            state: ConnectionState::Connected,
+            mode: AudioMode::Call,
+            last_error: None,
+        };
+        let (status, receiver) = watch::channel(vec![device.clone()]);
+        let (server, _received, cancel) = start_with_status(path.clone(), receiver);
+        wait_for_socket(&path).await;
+
+        let query = |path: PathBuf| tokio::task::spawn_blocking(move || query_status(&path));
+        assert_eq!(query(path.clone()).await.unwrap().unwrap(), [device.to_string()]);
+        status.send_replace(Vec::new());
+        assert!(query(path.clone()).await.unwrap().unwrap().is_empty());
+
+        cancel.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn silent_client_does_not_block_others() {
+        let dir = TempDir::new("ipc");
+        let path = dir.join("btmnr.sock");
+        let (server, mut received, cancel) = start(path.clone());
+        wait_for_socket(&path).await;
+
+        let _silent = UnixStream::connect(&path).await.unwrap();
+        let sent = timeout(Duration::from_secs(1), send_async(&path, ManagerCommand::Resume)).await;
+        assert!(sent.expect("second client was not served").is_ok());
+        assert_eq!(received.recv().await, Some(ManagerCommand::Resume));
+
+        cancel.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn replaces_stale_socket() {
+        let dir = TempDir::new("ipc");
+        let path = dir.join("btmnr.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let (server, mut received, cancel) = start(path.clone());
+        // Старый файл сокета уже есть, поэтому ждём, пока сервис ответит.
+        let mut sent = send_async(&path, ManagerCommand::ConnectNow).await;
+        while sent.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sent = send_async(&path, ManagerCommand::ConnectNow).await;
+        }
+        assert_eq!(received.recv().await, Some(ManagerCommand::ConnectNow));
+
+        cancel.cancel();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn leaves_other_files_and_live_sockets_alone() {
+        let dir = TempDir::new("ipc");
+        let file = dir.join("btmnr.sock");
+        std::fs::write(&file, "not a socket").unwrap();
+        let (server, _received, _cancel) = start(file.clone());
+        assert_eq!(server.await.unwrap().unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "not a socket");
+
+        let live = dir.join("live.sock");
+        let _listener = std::os::unix::net::UnixListener::bind(&live).unwrap();
+        let (server, _received, _cancel) = start(live.clone());
+        assert_eq!(server.await.unwrap().unwrap_err().kind(), ErrorKind::AddrInUse);
+        assert!(live.exists());
+    }
+}
@@ -1,129 +1,150 @@
-use windows_service::{
-    define_windows_service,
-    service_dispatcher,
-    service_control_handler::{self, ServiceControlHandler},
-    service::{
-        ServiceControl, ServiceControlAccept, ServiceExitCode,
-        ServiceState, ServiceStatus, ServiceType,
-    },
-};
-use log::{info, error};
+mod audio;
+mod bluetooth;
+mod cli;
+mod commands;
+mod config;
+mod connection;
+mod control;
+mod error;
+mod install;
+#[cfg(unix)]
+mod ipc;
+mod manager;
+mod migration;
+mod paths;
+mod retry;
+mod selection;
+#[cfg(windows)]
+mod service;
+mod switching;
+#[cfg(test)]
+mod testing;
+
+use log::{info, warn};
 use std::{
-    ffi::OsString,
-    sync::{Arc, atomic::{AtomicBool, Ordering}},
-    time::Duration
+    path::Path,
+    process::ExitCode
 };
-use tokio;
 
-struct BluetoothManager {
-    config_manager: ConfigManager,
-    running: Arc<AtomicBool>,
-}
+use cli::Command;
+use config::ConfigManager;
+use error::Error;
+use manager::{shutdown_runtime, BluetoothManager};
 
-impl BluetoothManager {
-    fn new() -> Self {
-        Self {
-            config_manager: ConfigManager::new(),
-            running: Arc::new(AtomicBool::new(true)),
+fn main() -> ExitCode {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            return ExitCode::from(2);
         }
-    }
-
-    async fn monitor_audio_activity(&self) {
-        let mut last_activity = std::time::Instant::now();
-        let mut consecutive_errors = 0;
-        
-        while self.running.load(Ordering::Relaxed) {
-            let config = self.config_manager.get_config();
-
-            match self.check_and_handle_audio(&mut last_activity, &config).await {
-                Ok(_) => {
-                    consecutive_errors = 0;
-                }
-                Err(e) => {
-                    error!("Error in audio monitoring: {:?}", e);
-                    consecutive_errors += 1;
-                    
-                    if consecutive_errors >= 3 {
-                        error!("Too many consecutive errors, waiting before retry");
-                        tokio::time::sleep(Duration::from_secs(30)).await;
-                        consecutive_errors = 0;
-                    }
-                }
-            }
+    };
+    let config_path = paths::config_path(args.config.as_deref());
 
-            tokio::time::sleep(Duration::from_secs(1)).await;
+    match execute(args.command, &config_path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
         }
     }
+}
 
-    async fn check_and_handle_audio(
-        &self,
-        last_activity: &mut std::time::Instant,
-        config: &Config,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if AudioMonitor::is_audio_playing()? {
-            *last_activity = std::time::Instant::now();
-            if config.auto_connect {
-                self.ensure_connected().await?;
-            }
-        } else if last_activity.elapsed() > Duration::from_secs(config.inactivity_timeout) {
-            self.disconnect_device().await?;
+fn execute(command: Command, config_path: &Path) -> Result<(), Error> {
+    match command {
+        Command::Run => run_foreground(config_path),
+        #[cfg(windows)]
+        Command::Service => service::run(config_path),
+        #[cfg(not(windows))]
+        Command::Service => Err(Error::Unsupported("service")),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
         }
-        Ok(())
+        Command::CheckConfig => commands::check_config(config_path),
+        Command::ConvertConfig { output } => commands::convert_config(config_path, &output),
+        Command::Control(command) => commands::send_control(command),
+        Command::Install => install::install(config_path),
+        Command::Uninstall => install::uninstall(),
+        command => run_once(command, config_path),
     }
 }
 
-fn main() -> Result<(), windows_service::Error> {
-    // Инициализация обработчика сервиса
-    let event_handler = move |control_event| -> ServiceControlHandlerResult {
-        match control_event {
-            ServiceControl::Stop | ServiceControl::Shutdown => {
-                info!("Service shutdown received");
-                running.store(false, Ordering::Relaxed);
-                ServiceControlHandlerResult::NoError
-            }
-            _ => ServiceControlHandlerResult::NotImplemented,
-        }
-    };
+// В консольном режиме лог идёт в stderr, а остановка по Ctrl-C или
+// SIGTERM проходит тем же путём, что и остановка службы.
+fn run_foreground(config_path: &Path) -> Result<(), Error> {
+    simple_logging::log_to_stderr(log::LevelFilter::Info);
 
-    // Регистрация обработчика
-    let status_handle = service_control_handler::register(
-        "BluetoothManager",
-        event_handler
-    )?;
+    info!("Using configuration {}", config_path.display());
+    let manager = BluetoothManager::new(ConfigManager::new(config_path.to_path_buf()));
+    let cancel = manager.cancellation_token();
+    let runtime = tokio::runtime::Runtime::new()?;
+    let drained = runtime.block_on(async {
+        #[cfg(unix)]
+        {
+            let commands = manager.command_sender();
+            let status = manager.status_receiver();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                let served = match ipc::socket_path() {
+                    Ok(path) => ipc::serve(&path, commands, status, cancel).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = served {
+                    warn!("Command socket is unavailable: {}", e);
+                }
+            });
+        }
 
-    // Обновление статуса сервиса
-    status_handle.set_service_status(ServiceStatus {
-        service_type: ServiceType::OWN_PROCESS,
-        current_state: ServiceState::Running,
-        controls_accepted: ServiceControlAccept::STOP,
-        exit_code: ServiceExitCode::Win32(0),
-        checkpoint: 0,
-        wait_hint: Duration::default(),
-        process_id: None,
-    })?;
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            info!("Shutdown requested, stopping");
+            cancel.cancel();
+        });
 
-    service_dispatcher::start("BluetoothManager", ffi_service_main)?;
+        manager.run().await
+    });
+    shutdown_runtime(runtime, drained);
+    info!("Stopped");
     Ok(())
 }
 
-fn service_main(arguments: Vec<OsString>) {
-    if let Err(e) = run_service(arguments) {
-        error!("Service error: {}", e);
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Failed to listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
     }
 }
 
-fn run_service(_arguments: Vec<OsString>) -> Result<(), Box<dyn std::error::Error>> {
-    simple_logging::log_to_file(
-        "bluetooth_manager.log",
-        log::LevelFilter::Info
-    )?;
+// Разовые команды пишут в консоль только предупреждения и ошибки,
+// чтобы их вывод не тонул в логе.
+fn run_once(command: Command, config_path: &Path) -> Result<(), Error> {
+    simple_logging::log_to_stderr(log::LevelFilter::Warn);
 
-    let manager = BluetoothManager::new();
-    
+    let config_manager = ConfigManager::load(config_path.to_path_buf())?;
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async {
-        manager.monitor_audio_activity().await;
-    });
-
-    Ok(())
+        match command {
+            Command::Connect { address } => commands::connect(&config_manager, address).await,
+            Command::Disconnect { address } => commands::disconnect(&config_manager, address).await,
+            Command::ListDevices => commands::list_devices(&config_manager).await,
+            Command::Status => commands::status(&config_manager).await,
+            _ => unreachable!("handled in execute"),
+        }
+    })
 }
@@ -0,0 +1,950 @@
+use log::{error, info, warn};
+use std::{
+    fmt,
+    sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}},
+    time::Duration
+};
+// Всё время берётся из tokio::time, чтобы цикл можно было гонять
+// на остановленных часах (`tokio::time::pause`) в тестах.
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::time::{sleep_until, timeout, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::audio::{self, AudioActivitySource};
+use crate::bluetooth::{self, BluetoothAddress, BluetoothError, ErrorCode, HeadsetLink, LinkFactory, LinkState, Profile};
+use crate::config::{Config, ConfigManager, DeviceConfig};
+use crate::connection::{Action, ConnectionMachine, ConnectionPolicy, ConnectionState, Event};
+use crate::control::ManagerCommand;
+use crate::error;
+use crate::selection;
+use crate::switching::{AudioMode, ModeDebouncer};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const ERROR_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_CONSECUTIVE_ERRORS: u32 = 3;
+
+pub struct BluetoothManager {
+    config_manager: ConfigManager,
+    audio: Box<dyn AudioActivitySource>,
+    link_factory: LinkFactory,
+    devices: Mutex<Vec<Arc<ManagedDevice>>>,
+    cancel: CancellationToken,
+    paused: AtomicBool,
+    commands_tx: UnboundedSender<ManagerCommand>,
+    commands_rx: tokio::sync::Mutex<UnboundedReceiver<ManagerCommand>>,
+    status: watch::Sender<Vec<DeviceStatus>>,
+}
+
+/// Снимок состояния одного устройства для команды `status`.
+#[derive(Clone, Debug)]
+pub struct DeviceStatus {
+    pub address: BluetoothAddress,
+    pub state: ConnectionState,
+    pub mode: AudioMode,
+    // Причина последнего неудачного подключения или отключения.
+    pub last_error: Option<(ErrorCode, String)>,
+}
+
+impl fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {:?} mode, ", self.address, self.mode)?;
+        match self.state {
+            ConnectionState::Failed { retry, attempts, retry_at: Some(retry_at) } => write!(
+                f,
+                "{:?} failed (attempt {}), retrying in {:.1}s",
+                retry, attempts, retry_at.saturating_duration_since(Instant::now()).as_secs_f64()
+            )?,
+            ConnectionState::Failed { retry, attempts, retry_at: None } => write!(
+                f,
+                "{:?} failed (attempt {}), giving up until audio state changes",
+                retry, attempts
+            )?,
+            ConnectionState::IdleGrace { since } => write!(f, "idle for {}s", since.elapsed().as_secs())?,
+            state => write!(f, "{:?}", state)?,
+        }
+        if let Some((code, message)) = &self.last_error {
+            write!(f, ", last error [{}]: {}", code, message)?;
+        }
+        Ok(())
+    }
+}
+
+struct ManagedDevice {
+    address: BluetoothAddress,
+    link: Box<dyn HeadsetLink>,
+    machine: Mutex<ConnectionMachine>,
+    mode: Mutex<ModeDebouncer>,
+    last_error: Mutex<Option<(ErrorCode, String)>>,
+}
+
+impl BluetoothManager {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self::with_backends(
+            config_manager,
+            audio::default_source(),
+            Box::new(bluetooth::default_link),
+        )
+    }
+
+    pub fn with_backends(
+        config_manager: ConfigManager,
+        audio: Box<dyn AudioActivitySource>,
+        link_factory: LinkFactory,
+    ) -> Self {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        Self {
+            config_manager,
+            audio,
+            link_factory,
+            devices: Mutex::new(Vec::new()),
+            cancel: CancellationToken::new(),
+            paused: AtomicBool::new(false),
+            commands_tx,
+            commands_rx: tokio::sync::Mutex::new(commands_rx),
+            status: watch::channel(Vec::new()).0,
+        }
+    }
+
+    pub fn status(&self) -> Vec<DeviceStatus> {
+        self.devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|device| DeviceStatus {
+                address: device.address,
+                state: device.machine.lock().unwrap().state(),
+                mode: device.mode.lock().unwrap().current(),
+                last_error: device.last_error.lock().unwrap().clone(),
+            })
+            .collect()
+    }
+
+    /// Последний снимок `status`, обновляемый между опросами и после
+    /// каждой команды; через него состояние читает IPC.
+    pub fn status_receiver(&self) -> watch::Receiver<Vec<DeviceStatus>> {
+        self.status.subscribe()
+    }
+
+    fn publish_status(&self) {
+        self.status.send_replace(self.status());
+    }
+
+    /// Канал команд SCM, CLI и IPC; команды выполняются между опросами.
+    pub fn command_sender(&self) -> UnboundedSender<ManagerCommand> {
+        self.commands_tx.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Отмена токена останавливает цикл мониторинга.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Работает до отмены токена, затем даёт текущим операциям не больше
+    /// `drain_timeout` на завершение, включая отключение устройств.
+    /// Возвращает `false`, если уложиться не удалось и операции брошены.
+    pub async fn run(&self) -> bool {
+        let monitor = self.monitor_audio_activity();
+        tokio::pin!(monitor);
+        let finished = tokio::select! {
+            _ = &mut monitor => true,
+            _ = self.cancel.cancelled() => false,
+        };
+
+        let drain = Duration::from_secs(self.config_manager.get_config().drain_timeout);
+        info!("Stopping, waiting up to {}s for in-flight operations", drain.as_secs());
+        let drained = timeout(drain, async {
+            if !finished {
+                (&mut monitor).await;
+            }
+            self.shutdown().await;
+        })
+        .await
+        .is_ok();
+
+        if !drained {
+            warn!("In-flight operations did not finish within {}s, abandoning them", drain.as_secs());
+        }
+        drained
+    }
+
+    pub async fn monitor_audio_activity(&self) {
+        let mut consecutive_errors = 0;
+
+        while !self.cancel.is_cancelled() {
+            if self.is_paused() {
+                self.wait(POLL_INTERVAL).await;
+                continue;
+            }
+
+            let config = self.config_manager.get_config();
+            match self.check_and_handle_audio(&config).await {
+                Ok(_) => {
+                    consecutive_errors = 0;
+                }
+                Err(e) if e.is_transient() => {
+                    warn!("Transient error in audio monitoring: {}", e);
+                    consecutive_errors += 1;
+
+                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                        error!("Too many consecutive errors, waiting before retry");
+                        self.wait(ERROR_BACKOFF).await;
+                        consecutive_errors = 0;
+                    }
+                }
+                Err(e) => {
+                    // Повтор через секунду ничего не изменит, поэтому сразу
+                    // выдерживаем длинную паузу.
+                    error!("Error in audio monitoring: {}", e);
+                    self.wait(ERROR_BACKOFF).await;
+                    consecutive_errors = 0;
+                }
+            }
+
+            self.wait(POLL_INTERVAL).await;
+        }
+    }
+
+    // Ожидание между опросами: пришедшие команды выполняются сразу,
+    // остановка сервиса прерывает ожидание.
+    async fn wait(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        let mut commands = self.commands_rx.lock().await;
+        self.publish_status();
+        loop {
+            tokio::select! {
+                _ = sleep_until(deadline) => return,
+                _ = self.cancel.cancelled() => return,
+                Some(command) = commands.recv() => {
+                    self.handle_command(command).await;
+                    self.publish_status();
+                }
+            }
+        }
+    }
+
+    async fn handle_command(&self, command: ManagerCommand) {
+        info!("Received command: {}", command);
+        let config = self.config_manager.get_config();
+        match command {
+            ManagerCommand::Pause => {
+                self.paused.store(true, Ordering::Relaxed);
+                info!("Automatic connection management paused");
+            }
+            ManagerCommand::Resume => {
+                // Пока сервис стоял, устройства могли подключить или
+                // отключить вручную.
+                for (device, device_config) in self.sync_devices(&config).await {
+                    device.sync_link_state(&connection_policy(&config, &device_config)).await;
+                }
+                self.paused.store(false, Ordering::Relaxed);
+                info!("Automatic connection management resumed");
+            }
+            ManagerCommand::ConnectNow => self.connect_now(&config).await,
+            ManagerCommand::DisconnectNow => self.disconnect_engaged(&config).await,
+            ManagerCommand::ReloadConfig => {
+                let _ = self.config_manager.reload();
+            }
+        }
+    }
+
+    // Подключает первое доступное устройство по приоритету, не глядя
+    // на auto_connect и на активность звука.
+    async fn connect_now(&self, config: &Config) {
+        let devices = self.sync_devices(config).await;
+        if devices.iter().any(|(device, _)| device.machine.lock().unwrap().state().is_engaged()) {
+            info!("A device is already connected");
+            return;
+        }
+
+        let device_configs: Vec<DeviceConfig> = devices.iter().map(|(_, d)| d.clone()).collect();
+        for device_config in selection::rank_candidates(&device_configs) {
+            let Some((device, _)) = devices.iter().find(|(d, _)| d.address == device_config.address) else {
+                continue;
+            };
+            let policy = connection_policy(config, device_config);
+            if device.perform(Action::Connect, device_config, &policy).await.is_ok() {
+                return;
+            }
+        }
+    }
+
+    async fn disconnect_engaged(&self, config: &Config) {
+        let devices: Vec<Arc<ManagedDevice>> = self.devices.lock().unwrap().clone();
+        for device in devices {
+            let Some(device_config) = config.devices.iter().find(|d| d.address == device.address) else {
+                continue;
+            };
+            if !device.machine.lock().unwrap().state().is_engaged() {
+                continue;
+            }
+            let _ = device
+                .perform(Action::Disconnect, device_config, &connection_policy(config, device_config))
+                .await;
+        }
+    }
+
+    /// Вызывается после выхода из цикла мониторинга: если так настроено,
+    /// отключает устройства, которые сервис держит подключёнными.
+    async fn shutdown(&self) {
+        let config = self.config_manager.get_config();
+        if config.disconnect_on_stop {
+            info!("Disconnecting devices before exit");
+            self.disconnect_engaged(&config).await;
+        }
+    }
+
+    pub async fn check_and_handle_audio(
+        &self,
+        config: &Config,
+    ) -> error::Result<()> {
+        let policy = config.activity_policy();
+        let devices = self.sync_devices(config).await;
+
+        // Микрофон опрашивается, только если хотя бы одно устройство
+        // переключает профили по нему.
+        let microphone_in_use = if devices.iter().any(|(_, d)| d.switches_on_microphone()) {
+            self.audio.is_microphone_in_use(&policy)?
+        } else {
+            false
+        };
+
+        let event = if self.audio.is_audio_playing(&policy)? || microphone_in_use {
+            Event::AudioActive
+        } else {
+            Event::AudioIdle
+        };
+
+        // Ошибки подключения не поднимаются наверх: у каждого устройства
+        // своя политика повторов, и общий back-off цикла их не касается.
+        match event {
+            Event::AudioActive => self.handle_audio_active(config, &devices).await,
+            _ => self.handle_event(config, &devices, event).await,
+        }
+        self.update_modes(config, &devices, microphone_in_use).await;
+        Ok(())
+    }
+
+    async fn update_modes(
+        &self,
+        config: &Config,
+        devices: &[(Arc<ManagedDevice>, DeviceConfig)],
+        microphone_in_use: bool,
+    ) {
+        let delay = Duration::from_secs(config.microphone_debounce);
+
+        for (device, device_config) in devices {
+            if !device_config.switches_on_microphone() {
+                continue;
+            }
+            let switched = device.mode.lock().unwrap().update(microphone_in_use, Instant::now(), delay);
+            let Some(mode) = switched else {
+                continue;
+            };
+            let connected = matches!(
+                device.machine.lock().unwrap().state(),
+                ConnectionState::Connected | ConnectionState::IdleGrace { .. }
+            );
+            // Неудавшееся переключение не запоминается и повторяется
+            // на следующем опросе; отключённое устройство просто подключится
+            // уже в новом режиме.
+            if connected {
+                if let Err(e) = device.switch_mode(device_config, mode).await {
+                    error!("Failed to switch device {} to {:?} mode: {}", device.address, mode, e);
+                    continue;
+                }
+            }
+            device.mode.lock().unwrap().commit(mode);
+        }
+    }
+
+    async fn handle_event(
+        &self,
+        config: &Config,
+        devices: &[(Arc<ManagedDevice>, DeviceConfig)],
+        event: Event,
+    ) {
+        for (device, device_config) in devices {
+            let connection_policy = connection_policy(config, device_config);
+            let action = device.machine.lock().unwrap().handle(event, Instant::now(), &connection_policy);
+            if let Some(action) = action {
+                match action {
+                    Action::Connect => info!("Audio activity detected for {}", device.address),
+                    Action::Disconnect => info!("Inactivity timeout reached for {}", device.address),
+                }
+                let _ = device.perform(action, device_config, &connection_policy).await;
+            }
+        }
+    }
+
+    // Если какое-то устройство уже подключено, звук просто продлевает его
+    // активность. Иначе устройства пробуются по приоритету, пока одно из
+    // них не подключится.
+    async fn handle_audio_active(
+        &self,
+        config: &Config,
+        devices: &[(Arc<ManagedDevice>, DeviceConfig)],
+    ) {
+        let engaged: Vec<_> = devices
+            .iter()
+            .filter(|(device, _)| device.machine.lock().unwrap().state().is_engaged())
+            .cloned()
+            .collect();
+        if !engaged.is_empty() {
+            self.handle_event(config, &engaged, Event::AudioActive).await;
+            return;
+        }
+
+        let device_configs: Vec<DeviceConfig> = devices.iter().map(|(_, d)| d.clone()).collect();
+        let mut skipped = Vec::new();
+        for device_config in selection::rank_candidates(&device_configs) {
+            let connection_policy = connection_policy(config, device_config);
+            if !connection_policy.auto_connect {
+                continue;
+            }
+            let Some((device, _)) = devices.iter().find(|(d, _)| d.address == device_config.address) else {
+                continue;
+            };
+
+            let action = device.machine.lock().unwrap().handle(Event::AudioActive, Instant::now(), &connection_policy);
+            let Some(action) = action else {
+                skipped.push((device.address, String::from("waiting to retry")));
+                continue;
+            };
+            info!("Audio activity detected, trying {}", device.address);
+
+            match device.perform(action, device_config, &connection_policy).await {
+                Ok(()) => {
+                    info!("{}", selection::describe_choice(device_config, &skipped));
+                    return;
+                }
+                Err(e) => match selection::fallback_reason(&e) {
+                    Some(reason) => {
+                        warn!("Device {} unavailable ({}), trying next candidate", device.address, reason);
+                        skipped.push((device.address, reason.to_string()));
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+
+    // Приводит список управляемых устройств в соответствие с конфигурацией:
+    // новые устройства получают свой link и начальное состояние, удалённые
+    // из конфигурации перестают обслуживаться.
+    async fn sync_devices(&self, config: &Config) -> Vec<(Arc<ManagedDevice>, DeviceConfig)> {
+        let mut added = Vec::new();
+        let devices = {
+            let mut devices = self.devices.lock().unwrap();
+            devices.retain(|device| config.devices.iter().any(|d| d.address == device.address));
+
+            for device_config in &config.devices {
+                if !devices.iter().any(|device| device.address == device_config.address) {
+                    info!("Managing device {}", device_config.address);
+                    let device = Arc::new(ManagedDevice {
+                        address: device_config.address,
+                        link: (self.link_factory)(device_config.address),
+                        machine: Mutex::new(ConnectionMachine::new(ConnectionState::Disconnected)),
+                        mode: Mutex::new(ModeDebouncer::new()),
+                        last_error: Mutex::new(None),
+                    });
+                    devices.push(device.clone());
+                    added.push((device, device_config.clone()));
+                }
+            }
+
+            config.devices
+                .iter()
+                .filter_map(|device_config| {
+                    devices
+                        .iter()
+                        .find(|device| device.address == device_config.address)
+                        .map(|device| (device.clone(), device_config.clone()))
+                })
+                .collect()
+        };
+
+        for (device, device_config) in added {
+            device.sync_link_state(&connection_policy(config, &device_config)).await;
+        }
+        devices
+    }
+}
+
+impl ManagedDevice {
+    async fn sync_link_state(&self, policy: &ConnectionPolicy) {
+        let event = match self.link.state().await {
+            Ok(LinkState::Connected) => Event::LinkUp,
+            Ok(LinkState::Disconnected) => Event::LinkDown,
+            Err(e) => {
+                warn!("Failed to query initial state of {}: {}", self.address, e);
+                return;
+            }
+        };
+        self.machine.lock().unwrap().handle(event, Instant::now(), policy);
+    }
+
+    async fn switch_mode(&self, device_config: &DeviceConfig, mode: AudioMode) -> Result<(), BluetoothError> {
+        let previous = match mode {
+            AudioMode::Call => AudioMode::Listening,
+            AudioMode::Listening => AudioMode::Call,
+        };
+        let old_profiles = device_config.profiles_for(previous);
+        let new_profiles = device_config.profiles_for(mode);
+        info!("Switching device {} to {:?} mode", self.address, mode);
+
+        for profile in new_profiles.iter().filter(|p| !old_profiles.contains(p)) {
+            self.link.set_profile(*profile, true).await?;
+        }
+        for profile in old_profiles.iter().filter(|p| !new_profiles.contains(p)) {
+            self.link.set_profile(*profile, false).await?;
+        }
+        Ok(())
+    }
+
+    async fn perform(
+        &self,
+        action: Action,
+        device_config: &DeviceConfig,
+        policy: &ConnectionPolicy,
+    ) -> Result<(), BluetoothError> {
+        let result = match action {
+            Action::Connect => {
+                let mode = self.mode.lock().unwrap().current();
+                info!("Connecting {} in {:?} mode", self.address, mode);
+                self.link.connect(device_config.profiles_for(mode)).await
+            }
+            Action::Disconnect => {
+                info!("Disconnecting {}", self.address);
+                self.link.disconnect(&device_config.all_profiles()).await
+            }
+        };
+
+        if let Ok(report) = &result {
+            if !report.failed.is_empty() {
+                let failed: Vec<String> = report.failed.iter().map(|(p, e)| format!("{} ({})", p, e)).collect();
+                let succeeded: Vec<String> = report.succeeded.iter().map(Profile::to_string).collect();
+                warn!(
+                    "Device {}: {:?} partially succeeded, ok: [{}], failed: [{}]",
+                    self.address, action, succeeded.join(", "), failed.join(", ")
+                );
+            }
+        }
+
+        let event = match (action, &result) {
+            (Action::Connect, Ok(_)) => Event::LinkUp,
+            (Action::Disconnect, Ok(_)) => Event::LinkDown,
+            (_, Err(e)) => Event::LinkFailed { transient: e.is_transient() },
+        };
+        let now = Instant::now();
+        self.machine.lock().unwrap().handle(event, now, policy);
+        *self.last_error.lock().unwrap() = result.as_ref().err().map(|e| (e.code(), e.to_string()));
+
+        if let Err(e) = &result {
+            let code = e.code();
+            match self.machine.lock().unwrap().state() {
+                ConnectionState::Failed { attempts, retry_at: Some(retry_at), .. } => error!(
+                    "Failed to {:?} device {} [{}] (attempt {}): {}, retrying in {:.1}s",
+                    action, self.address, code, attempts, e, retry_at.duration_since(now).as_secs_f64()
+                ),
+                ConnectionState::Failed { attempts, retry_at: None, .. } => error!(
+                    "Failed to {:?} device {} [{}] (attempt {}): {}, giving up until audio state changes",
+                    action, self.address, code, attempts, e
+                ),
+                _ => error!("Failed to {:?} device {} [{}]: {}", action, self.address, code, e),
+            }
+        }
+        result.map(|_| ())
+    }
+}
+
+/// Закрывает runtime после `BluetoothManager::run`. Брошенная по
+/// drain_timeout операция может всё ещё сидеть в блокирующем вызове
+/// (`spawn_blocking`), а drop runtime ждёт такие задачи без ограничения.
+/// Поэтому, если `run` не уложился, runtime закрывается без ожидания.
+pub fn shutdown_runtime(runtime: tokio::runtime::Runtime, drained: bool) {
+    if !drained {
+        warn!("Leaving abandoned operations running in the background");
+        runtime.shutdown_background();
+    }
+}
+
+fn connection_policy(config: &Config, device: &DeviceConfig) -> ConnectionPolicy {
+    ConnectionPolicy {
+        inactivity_timeout: Duration::from_secs(config.inactivity_timeout_for(device)),
+        auto_connect: config.auto_connect_for(device),
+        retry: device.retry.unwrap_or(config.retry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use tokio::task::JoinHandle;
+    use tokio::time::sleep;
+
+    use super::*;
+    use crate::audio::{AudioError, Flow, ScriptedAudioSource};
+    use crate::bluetooth::{DeviceInfo, ProfileReport};
+    use crate::retry::RetryPolicy;
+    use crate::bluetooth::SimulatedHeadset;
+
+    const ADDRESS: &str = "00:11:22:33:44:55";
+
+    struct Harness {
+        manager: Arc<BluetoothManager>,
+        audio: Arc<ScriptedAudioSource>,
+        headset: Arc<SimulatedHeadset>,
+    }
+
+    impl Harness {
+        fn new(config: Config) -> Self {
+            let audio = Arc::new(ScriptedAudioSource::idle());
+            let headset = Arc::new(SimulatedHeadset::new(ADDRESS.parse().unwrap()));
+            let link = headset.clone();
+            let manager = BluetoothManager::with_backends(
+                ConfigManager::with_config(PathBuf::from("config.json"), config),
+                Box::new(audio.clone()),
+                Box::new(move |_| Box::new(link.clone())),
+            );
+            Self { manager: Arc::new(manager), audio, headset }
+        }
+
+        fn start(&self) -> JoinHandle<bool> {
+            let manager = self.manager.clone();
+            tokio::spawn(async move { manager.run().await })
+        }
+
+        fn state(&self) -> ConnectionState {
+            self.manager.devices.lock().unwrap()[0].machine.lock().unwrap().state()
+        }
+
+        async fn stop(&self, run: JoinHandle<bool>) -> bool {
+            self.manager.cancellation_token().cancel();
+            run.await.unwrap()
+        }
+    }
+
+    fn config(inactivity_timeout: u64) -> Config {
+        Config {
+            inactivity_timeout,
+            devices: vec![DeviceConfig::new(ADDRESS.parse().unwrap())],
+            ..Config::default()
+        }
+    }
+
+    // Опросы идут на целых секундах, проверки — между ними.
+    async fn sleep_secs(secs: f64) {
+        sleep(Duration::from_secs_f64(secs)).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connects_on_audio_and_disconnects_after_timeout() {
+        let harness = Harness::new(config(10));
+        harness.audio.push(true);
+        harness.audio.push(false);
+        let run = harness.start();
+
+        sleep_secs(0.5).await;
+        assert!(harness.headset.is_connected());
+        // Тишина началась на первой секунде, отключение — через 10 секунд.
+        sleep_secs(10.0).await;
+        assert!(harness.headset.is_connected());
+        sleep_secs(1.0).await;
+        assert!(!harness.headset.is_connected());
+        assert_eq!((harness.headset.connect_calls(), harness.headset.disconnect_calls()), (1, 1));
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_after_repeated_audio_errors() {
+        let harness = Harness::new(config(10));
+        for _ in 0..MAX_CONSECUTIVE_ERRORS {
+            harness.audio.push_error(Flow::Render, AudioError::BackendError(String::from("restarting")));
+        }
+        harness.audio.push(true);
+        let run = harness.start();
+
+        // Ошибки на 0-й, 1-й и 2-й секундах, затем 30 секунд паузы
+        // и обычный интервал опроса: следующая проверка на 33-й секунде.
+        sleep_secs(32.5).await;
+        assert_eq!(harness.headset.connect_calls(), 0);
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.connect_calls(), 1);
+
+        assert!(harness.stop(run).await);
+    }
+
+    fn retrying_config(max_attempts: u32) -> Config {
+        let retry = RetryPolicy { initial_delay: 2.0, multiplier: 2.0, max_delay: 5.0, jitter: 0.0, max_attempts };
+        Config { retry, ..config(10) }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_failed_connects_with_growing_delay() {
+        let harness = Harness::new(retrying_config(0));
+        harness.headset.fail_next_connects(3);
+        harness.audio.push(true);
+        let run = harness.start();
+
+        // Неудачи на 0-й, 2-й и 6-й секундах; третья задержка упирается
+        // в max_delay, поэтому следующая попытка на 11-й, а не на 14-й.
+        sleep_secs(10.5).await;
+        assert_eq!(harness.headset.connect_calls(), 3);
+        let retry_at = Some(Instant::now() + Duration::from_millis(500));
+        assert_eq!(harness.state(), ConnectionState::Failed { retry: Action::Connect, attempts: 3, retry_at });
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.connect_calls(), 4);
+        assert_eq!(harness.state(), ConnectionState::Connected);
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn status_reports_attempts_and_next_retry() {
+        let harness = Harness::new(retrying_config(0));
+        harness.headset.fail_next_connects(2);
+        harness.audio.push(true);
+        let mut status = harness.manager.status_receiver();
+        let run = harness.start();
+
+        // Вторая неудача на 2-й секунде, следующая попытка — на 6-й.
+        sleep_secs(2.5).await;
+        assert!(status.has_changed().unwrap());
+        let devices = status.borrow_and_update().clone();
+        assert_eq!(devices.len(), 1);
+        assert!(matches!(devices[0].state, ConnectionState::Failed { attempts: 2, retry_at: Some(_), .. }));
+        assert_eq!(devices[0].last_error.as_ref().map(|(code, _)| *code), Some(ErrorCode::DeviceNotFound));
+        assert_eq!(
+            devices[0].to_string(),
+            format!(
+                "{}  Listening mode, Connect failed (attempt 2), retrying in 3.5s, last error [{}]: Device {} not found",
+                ADDRESS, ErrorCode::DeviceNotFound, ADDRESS
+            )
+        );
+
+        sleep_secs(4.0).await;
+        let devices = harness.manager.status();
+        assert_eq!(devices[0].state, ConnectionState::Connected);
+        assert!(devices[0].last_error.is_none());
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_until_audio_goes_idle() {
+        let harness = Harness::new(retrying_config(2));
+        harness.headset.fail_next_connects(3);
+        harness.audio.push(true);
+        let run = harness.start();
+
+        sleep_secs(30.5).await;
+        assert_eq!(harness.headset.connect_calls(), 2);
+        assert!(matches!(harness.state(), ConnectionState::Failed { attempts: 2, retry_at: None, .. }));
+
+        // Пауза в звуке на 31-й секунде сбрасывает счётчик попыток.
+        harness.audio.push(false);
+        harness.audio.push(true);
+        sleep_secs(2.0).await;
+        assert_eq!(harness.headset.connect_calls(), 3);
+        assert!(matches!(harness.state(), ConnectionState::Failed { attempts: 1, retry_at: Some(_), .. }));
+        sleep_secs(2.0).await;
+        assert_eq!(harness.headset.connect_calls(), 4);
+        assert_eq!(harness.state(), ConnectionState::Connected);
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_link_error_is_not_retried() {
+        let harness = Harness::new(retrying_config(0));
+        harness.headset.set_paired(false);
+        harness.audio.push(true);
+        let run = harness.start();
+
+        sleep_secs(60.5).await;
+        assert_eq!(harness.headset.connect_calls(), 1);
+        assert!(matches!(harness.state(), ConnectionState::Failed { attempts: 1, retry_at: None, .. }));
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_audio_error_backs_off_at_once() {
+        let harness = Harness::new(config(10));
+        harness.audio.push_error(Flow::Render, AudioError::BackendUnavailable(String::from("pactl is not installed")));
+        harness.audio.push(true);
+        let run = harness.start();
+
+        // Ошибка на 0-й секунде, затем 30 секунд паузы и интервал опроса.
+        sleep_secs(30.5).await;
+        assert_eq!(harness.headset.connect_calls(), 0);
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.connect_calls(), 1);
+
+        assert!(harness.stop(run).await);
+    }
+
+    fn draining_config() -> Config {
+        Config { disconnect_on_stop: true, drain_timeout: 5, ..config(10) }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_waits_for_slow_disconnect() {
+        let harness = Harness::new(draining_config());
+        harness.audio.push(true);
+        let run = harness.start();
+        sleep_secs(0.5).await;
+        harness.headset.set_latency(Duration::from_secs(3));
+
+        let stopping = Instant::now();
+        assert!(harness.stop(run).await);
+        assert_eq!(stopping.elapsed(), Duration::from_secs(3));
+        assert!(!harness.headset.is_connected());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_abandons_operations_after_drain_timeout() {
+        let harness = Harness::new(draining_config());
+        harness.audio.push(true);
+        let run = harness.start();
+        sleep_secs(0.5).await;
+        harness.headset.set_latency(Duration::from_secs(3600));
+
+        let stopping = Instant::now();
+        assert!(!harness.stop(run).await);
+        assert_eq!(stopping.elapsed(), Duration::from_secs(5));
+        assert_eq!(harness.headset.disconnect_calls(), 1);
+    }
+
+    fn switching_config() -> Config {
+        let mut config = Config { microphone_debounce: 3, ..config(60) };
+        config.devices[0].profiles = vec![Profile::A2dpSink];
+        config.devices[0].call_profiles = vec![Profile::HandsFree];
+        config
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn switches_to_call_profiles_while_microphone_is_in_use() {
+        let harness = Harness::new(switching_config());
+        harness.audio.push(true);
+        harness.audio.push_microphone(false);
+        harness.audio.push_microphone(true);
+        let run = harness.start();
+
+        // Микрофон занят с 1-й секунды, переключение — через 3 секунды.
+        sleep_secs(3.5).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::A2dpSink]));
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::HandsFree]));
+
+        // Освободился на 5-й секунде, обратно — на 8-й.
+        harness.audio.push_microphone(false);
+        sleep_secs(3.0).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::HandsFree]));
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::A2dpSink]));
+        assert_eq!(harness.headset.connect_calls(), 1);
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_mode_switch_is_retried() {
+        let harness = Harness::new(switching_config());
+        harness.audio.push(true);
+        harness.audio.push_microphone(false);
+        harness.audio.push_microphone(true);
+        let run = harness.start();
+
+        sleep_secs(3.5).await;
+        harness.headset.fail_next_profile_changes(1);
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::A2dpSink]));
+        assert_eq!(harness.manager.devices.lock().unwrap()[0].mode.lock().unwrap().current(), AudioMode::Listening);
+        sleep_secs(1.0).await;
+        assert_eq!(harness.headset.enabled_profiles(), HashSet::from([Profile::HandsFree]));
+        assert_eq!(harness.manager.devices.lock().unwrap()[0].mode.lock().unwrap().current(), AudioMode::Call);
+
+        assert!(harness.stop(run).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_are_handled_between_polls() {
+        let harness = Harness::new(config(10));
+        let run = harness.start();
+
+        sleep_secs(0.5).await;
+        harness.manager.command_sender().send(ManagerCommand::ConnectNow).unwrap();
+        sleep_secs(0.1).await;
+        assert!(harness.headset.is_connected());
+
+        harness.manager.command_sender().send(ManagerCommand::Pause).unwrap();
+        sleep_secs(0.1).await;
+        assert!(harness.manager.is_paused());
+
+        assert!(harness.stop(run).await);
+    }
+
+    /// Link, который, как BluetoothController, отключается блокирующим
+    /// вызовом в spawn_blocking, причём дольше любого drain_timeout.
+    #[derive(Default)]
+    struct BlockingLink {
+        connected: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl HeadsetLink for BlockingLink {
+        async fn find_device(&self) -> Result<DeviceInfo, BluetoothError> {
+            unimplemented!("not used by the manager loop")
+        }
+
+        async fn state(&self) -> Result<LinkState, BluetoothError> {
+            Ok(if self.connected.load(Ordering::SeqCst) { LinkState::Connected } else { LinkState::Disconnected })
+        }
+
+        async fn set_profile(&self, _profile: Profile, _enabled: bool) -> Result<(), BluetoothError> {
+            Ok(())
+        }
+
+        async fn connect(&self, profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+            self.connected.store(true, Ordering::SeqCst);
+            Ok(ProfileReport { succeeded: profiles.to_vec(), failed: Vec::new() })
+        }
+
+        async fn disconnect(&self, _profiles: &[Profile]) -> Result<ProfileReport, BluetoothError> {
+            tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_secs(3600))).await.unwrap();
+            Ok(ProfileReport::default())
+        }
+    }
+
+    #[test]
+    fn abandoned_blocking_call_does_not_hold_the_runtime() {
+        let config = Config { disconnect_on_stop: true, drain_timeout: 1, ..config(10) };
+        let manager = BluetoothManager::with_backends(
+            ConfigManager::with_config(PathBuf::from("config.json"), config),
+            Box::new(ScriptedAudioSource::new([true])),
+            Box::new(|_| Box::new(BlockingLink::default())),
+        );
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let started = std::time::Instant::now();
+        let drained = runtime.block_on(async {
+            let cancel = manager.cancellation_token();
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(200)).await;
+                cancel.cancel();
+            });
+            manager.run().await
+        });
+        assert!(!drained);
+        shutdown_runtime(runtime, drained);
+        assert!(started.elapsed() < Duration::from_secs(30), "runtime waited for the blocking call");
+    }
+}
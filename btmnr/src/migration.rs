@@ -0,0 +1,74 @@
+use serde_json::{json, Map, Value};
+
+use crate::bluetooth::BluetoothAddress;
+use crate::config::ConfigError;
+
+/// Версия схемы, которую понимает `Config`.
+pub const CURRENT_VERSION: u32 = 1;
+
+// Миграция возвращает true, если изменила что-то кроме номера версии.
+type Migration = fn(&mut Map<String, Value>) -> bool;
+
+// MIGRATIONS[n] переводит конфигурацию версии n в версию n + 1.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1];
+
+/// Версия схемы разобранного файла. Файлы без поля `version` считаются
+/// версией 0.
+pub fn version(config: &Value) -> Result<u32, ConfigError> {
+    let version = match config.get("version") {
+        None => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| ConfigError::Invalid(format!("version must be a non-negative integer, found {}", value)))?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(ConfigError::UnsupportedVersion { found: version, supported: CURRENT_VERSION });
+    }
+    Ok(version)
+}
+
+/// Последовательно применяет миграции, начиная с `from`, и проставляет
+/// версию после каждого шага. Возвращает true, если содержимое изменилось
+/// не только номером версии.
+pub fn migrate(config: &mut Value, from: u32) -> Result<bool, ConfigError> {
+    let Value::Object(map) = config else {
+        return Err(ConfigError::Invalid(String::from("configuration must be a table of settings")));
+    };
+    let mut changed = false;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        changed |= migration(map);
+        map.insert(String::from("version"), json!(version + 1));
+        log::info!("Migrated configuration from version {} to {}", version, version + 1);
+    }
+    Ok(changed)
+}
+
+// Версия 0 — исходный формат из трёх полей: inactivity_timeout,
+// auto_connect и device_address. Позже появился список devices, но поле
+// version в него не добавлялось, поэтому в файлах версии 0 может быть и то,
+// и другое. Одиночный адрес становится первым устройством списка.
+fn v0_to_v1(config: &mut Map<String, Value>) -> bool {
+    let Some(address) = config.remove("device_address").filter(|address| !address.is_null()) else {
+        return false;
+    };
+    let devices = config.entry("devices").or_insert_with(|| Value::Array(Vec::new()));
+    // Если devices задан не списком, об этом сообщит разбор Config.
+    if let Value::Array(devices) = devices {
+        let configured = devices
+            .iter()
+            .any(|device| same_address(device.get("address").or_else(|| device.get("device_address")), &address));
+        if !configured {
+            devices.insert(0, json!({ "address": address }));
+        }
+    }
+    true
+}
+
+fn same_address(configured: Option<&Value>, address: &Value) -> bool {
+    let parse = |value: &Value| value.as_str().and_then(|text| text.parse::<BluetoothAddress>().ok());
+    match (configured.and_then(parse), parse(address)) {
+        (Some(configured), Some(address)) => configured == address,
+        _ => configured == Some(address),
+    }
+}
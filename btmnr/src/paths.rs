@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+/// Переменная окружения, переопределяющая путь к конфигурации.
+pub const CONFIG_ENV: &str = "BTMNR_CONFIG";
+
+const APP_DIR: &str = "btmnr";
+// Если в каталоге лежит несколько файлов, берётся первый по этому списку;
+// если нет ни одного, используется config.json.
+const CONFIG_FILES: [&str; 4] = ["config.json", "config.toml", "config.yaml", "config.yml"];
+
+/// Путь к конфигурации: флаг `--config`, затем `BTMNR_CONFIG`, затем
+/// путь по умолчанию для платформы.
+pub fn config_path(flag: Option<&Path>) -> PathBuf {
+    if let Some(path) = flag {
+        return path.to_path_buf();
+    }
+    match std::env::var_os(CONFIG_ENV) {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => default_config_path(),
+    }
+}
+
+#[cfg(windows)]
+pub fn default_config_path() -> PathBuf {
+    let program_data = std::env::var_os("ProgramData")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"));
+    find_config(&program_data.join(APP_DIR))
+}
+
+// Относительный путь остаётся только на случай, когда домашний каталог
+// неизвестен. Службе он не передаётся: install делает путь абсолютным.
+#[cfg(not(windows))]
+pub fn default_config_path() -> PathBuf {
+    match config_home() {
+        Some(dir) => find_config(&dir.join(APP_DIR)),
+        None => find_config(Path::new("")),
+    }
+}
+
+fn find_config(dir: &Path) -> PathBuf {
+    CONFIG_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| dir.join(CONFIG_FILES[0]))
+}
+
+/// `$XDG_CONFIG_HOME`, а если он не задан — `$HOME/.config`.
+#[cfg(not(windows))]
+pub fn config_home() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .map(|home| Path::new(&home).join(".config")),
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    // Тесты меняют переменные окружения процесса.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn with_env<T>(config: Option<&Path>, config_home: &Path, test: impl FnOnce() -> T) -> T {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        match config {
+            Some(path) => std::env::set_var(CONFIG_ENV, path),
+            None => std::env::remove_var(CONFIG_ENV),
+        }
+        std::env::set_var("XDG_CONFIG_HOME", config_home);
+        let result = test();
+        std::env::remove_var(CONFIG_ENV);
+        result
+    }
+
+    #[test]
+    fn flag_wins_over_env_and_default() {
+        let dir = TempDir::new("paths");
+        let flag = dir.join("flag.toml");
+        let env = dir.join("env.yaml");
+
+        let path = with_env(Some(&env), &dir.join("home"), || config_path(Some(&flag)));
+        assert_eq!(path, flag);
+    }
+
+    #[test]
+    fn env_wins_over_default() {
+        let dir = TempDir::new("paths");
+        let env = dir.join("env.yaml");
+
+        assert_eq!(with_env(Some(&env), &dir.join("home"), || config_path(None)), env);
+        // Пустая переменная считается незаданной.
+        let path = with_env(Some(Path::new("")), &dir.join("home"), || config_path(None));
+        assert_eq!(path, dir.join("home").join(APP_DIR).join("config.json"));
+    }
+
+    #[test]
+    fn default_picks_existing_file_in_config_home() {
+        let dir = TempDir::new("paths");
+        let app_dir = dir.join("home").join(APP_DIR);
+
+        let path = with_env(None, &dir.join("home"), || config_path(None));
+        assert_eq!(path, app_dir.join("config.json"));
+
+        std::fs::create_dir_all(&app_dir).unwrap();
+        std::fs::write(app_dir.join("config.yml"), "").unwrap();
+        std::fs::write(app_dir.join("config.toml"), "").unwrap();
+        let path = with_env(None, &dir.join("home"), || config_path(None));
+        assert_eq!(path, app_dir.join("config.toml"));
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Политика повторных попыток с экспоненциальной задержкой.
+/// Задержки указываются в секундах; `max_attempts == 0` — без ограничения.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub initial_delay: f64,
+    pub multiplier: f64,
+    pub max_delay: f64,
+    pub jitter: f64,
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: 2.0,
+            multiplier: 2.0,
+            max_delay: 60.0,
+            jitter: 0.1,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Задержка перед следующей попыткой после `failures` неудач подряд.
+    /// `sample` — случайное число из [0, 1), задающее разброс.
+    pub fn delay(&self, failures: u32, sample: f64) -> Duration {
+        let exponent = failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = (self.initial_delay * self.multiplier.powi(exponent)).min(self.max_delay);
+        let spread = base * self.jitter * (2.0 * sample - 1.0);
+        Duration::from_secs_f64((base + spread).clamp(0.0, self.max_delay))
+    }
+
+    pub fn exhausted(&self, failures: u32) -> bool {
+        self.max_attempts != 0 && failures >= self.max_attempts
+    }
+
+    /// Проверяет все поля и возвращает список проблем: имя поля и описание.
+    pub fn validate(&self) -> Vec<(&'static str, String)> {
+        let mut problems = Vec::new();
+        if !(self.initial_delay > 0.0 && self.initial_delay <= MAX_DELAY_SECS) {
+            problems.push(("initial_delay", format!("must be greater than 0 and at most {}", MAX_DELAY_SECS)));
+        }
+        if !(1.0..=MAX_MULTIPLIER).contains(&self.multiplier) {
+            problems.push(("multiplier", format!("must be between 1.0 and {}", MAX_MULTIPLIER)));
+        }
+        if self.max_delay.is_nan() || self.max_delay > MAX_DELAY_SECS {
+            problems.push(("max_delay", format!("must be at most {}", MAX_DELAY_SECS)));
+        } else if self.max_delay < self.initial_delay {
+            problems.push(("max_delay", String::from("must not be less than initial_delay")));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            problems.push(("jitter", String::from("must be between 0.0 and 1.0")));
+        }
+        problems
+    }
+}
+
+// Задержка больше суток означает ошибку в конфигурации, а не намерение.
+const MAX_DELAY_SECS: f64 = 24.0 * 60.0 * 60.0;
+const MAX_MULTIPLIER: f64 = 10.0;
+
+/// Небольшой xorshift-генератор для разброса задержек. Криптостойкость
+/// здесь не нужна, а детерминированное зерно упрощает тесты.
+#[derive(Clone, Debug)]
+pub struct Jitter {
+    state: u64,
+}
+
+impl Jitter {
+    pub fn new() -> Self {
+        Self::with_seed(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn sample(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,93 @@
+use std::cmp::Reverse;
+
+use crate::bluetooth::{BluetoothAddress, BluetoothError};
+use crate::config::DeviceConfig;
+
+/// Порядок, в котором устройства пробуются при появлении звука:
+/// по убыванию `priority`, при равенстве — в порядке из конфигурации.
+pub fn rank_candidates(devices: &[DeviceConfig]) -> Vec<&DeviceConfig> {
+    let mut ranked: Vec<&DeviceConfig> = devices.iter().collect();
+    ranked.sort_by_key(|device| Reverse(device.priority));
+    ranked
+}
+
+/// Ошибки, после которых имеет смысл перейти к следующему устройству.
+pub fn fallback_reason(error: &BluetoothError) -> Option<&'static str> {
+    match error {
+        BluetoothError::DeviceNotFound { .. } => Some("device not found"),
+        BluetoothError::AuthenticationError { .. } => Some("authentication failed"),
+        _ => None,
+    }
+}
+
+pub fn describe_choice(chosen: &DeviceConfig, skipped: &[(BluetoothAddress, String)]) -> String {
+    let mut description = format!("Selected device {} (priority {})", chosen.address, chosen.priority);
+    if skipped.is_empty() {
+        description.push_str(": highest-priority candidate");
+    } else {
+        let reasons: Vec<String> = skipped
+            .iter()
+            .map(|(address, reason)| format!("{} {}", address, reason))
+            .collect();
+        description.push_str(&format!(": skipped {}", reasons.join(", ")));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bluetooth::Profile;
+
+    fn device(address: &str, priority: i32) -> DeviceConfig {
+        DeviceConfig { priority, ..DeviceConfig::new(address.parse().unwrap()) }
+    }
+
+    #[test]
+    fn ranks_by_priority_then_config_order() {
+        let devices = [
+            device("00:00:00:00:00:01", 0),
+            device("00:00:00:00:00:02", 10),
+            device("00:00:00:00:00:03", 0),
+            device("00:00:00:00:00:04", -5),
+            device("00:00:00:00:00:05", 10),
+        ];
+
+        let order: Vec<String> = rank_candidates(&devices).iter().map(|d| d.address.to_string()).collect();
+        assert_eq!(
+            order,
+            ["00:00:00:00:00:02", "00:00:00:00:00:05", "00:00:00:00:00:01", "00:00:00:00:00:03", "00:00:00:00:00:04"]
+        );
+    }
+
+    #[test]
+    fn falls_back_only_when_another_device_may_help() {
+        let address = "00:11:22:33:44:55".parse().unwrap();
+
+        assert_eq!(fallback_reason(&BluetoothError::DeviceNotFound { address }), Some("device not found"));
+        assert_eq!(
+            fallback_reason(&BluetoothError::AuthenticationError { address, source: None }),
+            Some("authentication failed")
+        );
+        assert_eq!(
+            fallback_reason(&BluetoothError::ServiceStateError { address, profile: Profile::A2dpSink, source: None }),
+            None
+        );
+        assert_eq!(fallback_reason(&BluetoothError::EnumerationError { source: None }), None);
+    }
+
+    #[test]
+    fn describes_skipped_candidates() {
+        let chosen = device("00:00:00:00:00:02", 3);
+        let skipped = ["00:00:00:00:00:01".parse().unwrap()].map(|address| (address, String::from("device not found")));
+
+        assert_eq!(
+            describe_choice(&chosen, &[]),
+            "Selected device 00:00:00:00:00:02 (priority 3): highest-priority candidate"
+        );
+        assert_eq!(
+            describe_choice(&chosen, &skipped),
+            "Selected device 00:00:00:00:00:02 (priority 3): skipped 00:00:00:00:00:01 device not found"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use windows_service::{
+    define_windows_service,
+    service_dispatcher,
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode,
+        ServiceState, ServiceStatus, ServiceType,
+    },
+};
+use log::{info, error};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::Duration
+};
+
+use crate::config::ConfigManager;
+use crate::control::ManagerCommand;
+use crate::error::Error;
+use crate::install::SERVICE_NAME;
+use crate::manager::BluetoothManager;
+
+const ACCEPTED_CONTROLS: ServiceControlAccept = ServiceControlAccept::STOP
+    .union(ServiceControlAccept::SHUTDOWN)
+    .union(ServiceControlAccept::PAUSE_CONTINUE);
+
+// Как часто во время остановки сообщать SCM, что служба ещё жива.
+const STOP_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+// service_main вызывается диспетчером SCM, поэтому путь к конфигурации
+// передаётся ему через статическую переменную.
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+pub fn run(config_path: &Path) -> Result<(), Error> {
+    let _ = CONFIG_PATH.set(config_path.to_path_buf());
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+    Ok(())
+}
+
+fn service_main(arguments: Vec<OsString>) {
+    if let Err(e) = run_service(arguments) {
+        error!("Service error: {}", e);
+    }
+}
+
+fn run_service(_arguments: Vec<OsString>) -> Result<(), Error> {
+    simple_logging::log_to_file(
+        "bluetooth_manager.log",
+        log::LevelFilter::Info
+    )?;
+
+    let config_path = CONFIG_PATH.get().cloned().unwrap_or_else(crate::paths::default_config_path);
+    info!("Using configuration {}", config_path.display());
+    let manager = BluetoothManager::new(ConfigManager::new(config_path));
+    let cancel = manager.cancellation_token();
+
+    let commands = manager.command_sender();
+    // Дескриптор статуса появляется только после регистрации обработчика,
+    // а нужен самому обработчику, чтобы сообщать о паузе.
+    let handler_status: Arc<OnceLock<ServiceStatusHandle>> = Arc::new(OnceLock::new());
+
+    // Инициализация обработчика сервиса
+    let handler_cancel = cancel.clone();
+    let status_cell = handler_status.clone();
+    let event_handler = move |control_event| -> ServiceControlHandlerResult {
+        let command = match control_event {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                info!("Service shutdown received");
+                handler_cancel.cancel();
+                return ServiceControlHandlerResult::NoError;
+            }
+            ServiceControl::Interrogate => return ServiceControlHandlerResult::NoError,
+            ServiceControl::Pause => ManagerCommand::Pause,
+            ServiceControl::Continue => ManagerCommand::Resume,
+            ServiceControl::UserEvent(code) => match ManagerCommand::from_control_code(code.to_raw()) {
+                Some(command) => command,
+                None => return ServiceControlHandlerResult::NotImplemented,
+            },
+            _ => return ServiceControlHandlerResult::NotImplemented,
+        };
+
+        if commands.send(command).is_err() {
+            return ServiceControlHandlerResult::NoError;
+        }
+        let state = match command {
+            ManagerCommand::Pause => Some(ServiceState::Paused),
+            ManagerCommand::Resume => Some(ServiceState::Running),
+            _ => None,
+        };
+        if let (Some(state), Some(handle)) = (state, status_cell.get()) {
+            if let Err(e) = handle.set_service_status(service_status(state, ACCEPTED_CONTROLS, 0)) {
+                error!("Failed to report {:?}: {}", state, e);
+            }
+        }
+        ServiceControlHandlerResult::NoError
+    };
+
+    // Регистрация обработчика
+    let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)?;
+    let _ = handler_status.set(status_handle);
+
+    // Обновление статуса сервиса
+    status_handle.set_service_status(service_status(ServiceState::Running, ACCEPTED_CONTROLS, 0))?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    // Пока идёт остановка, SCM получает StopPending с растущим checkpoint,
+    // иначе он сочтёт службу зависшей. Отчёт идёт отдельной задачей на
+    // рабочих потоках runtime, чтобы не зависеть от того, чем занят цикл.
+    let report_stop_pending = runtime.spawn(async move {
+        cancel.cancelled().await;
+        let mut checkpoint = 1;
+        loop {
+            let status = service_status(ServiceState::StopPending, ServiceControlAccept::empty(), checkpoint);
+            if let Err(e) = status_handle.set_service_status(status) {
+                error!("Failed to report StopPending: {}", e);
+            }
+            checkpoint += 1;
+            tokio::time::sleep(STOP_CHECKPOINT_INTERVAL).await;
+        }
+    });
+
+    let drained = runtime.block_on(async {
+        let drained = manager.run().await;
+        // Stopped не должен обогнать последний StopPending.
+        report_stop_pending.abort();
+        let _ = report_stop_pending.await;
+        drained
+    });
+    crate::manager::shutdown_runtime(runtime, drained);
+
+    status_handle.set_service_status(service_status(ServiceState::Stopped, ServiceControlAccept::empty(), 0))?;
+    Ok(())
+}
+
+fn service_status(
+    current_state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    checkpoint: u32,
+) -> ServiceStatus {
+    let wait_hint = if current_state == ServiceState::StopPending {
+        STOP_CHECKPOINT_INTERVAL * 2
+    } else {
+        Duration::default()
+    };
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint,
+        wait_hint,
+        process_id: None,
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioMode {
+    Listening,
+    Call,
+}
+
+/// Переключает режим только после того, как микрофон непрерывно занят
+/// (или свободен) дольше `delay`, чтобы короткие всплески не дёргали профили.
+#[derive(Clone, Debug)]
+pub struct ModeDebouncer {
+    current: AudioMode,
+    pending_since: Option<Instant>,
+}
+
+impl ModeDebouncer {
+    pub fn new() -> Self {
+        Self {
+            current: AudioMode::Listening,
+            pending_since: None,
+        }
+    }
+
+    pub fn current(&self) -> AudioMode {
+        self.current
+    }
+
+    /// Возвращает режим, на который пора переключиться. Текущим он
+    /// становится только после `commit`: пока переключение не удалось,
+    /// каждый следующий опрос возвращает его снова.
+    pub fn update(&mut self, microphone_in_use: bool, now: Instant, delay: Duration) -> Option<AudioMode> {
+        let wanted = if microphone_in_use { AudioMode::Call } else { AudioMode::Listening };
+        if wanted == self.current {
+            self.pending_since = None;
+            return None;
+        }
+
+        let since = *self.pending_since.get_or_insert(now);
+        (now.duration_since(since) >= delay).then_some(wanted)
+    }
+
+    pub fn commit(&mut self, mode: AudioMode) {
+        self.current = mode;
+        self.pending_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(3);
+
+    fn at(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn short_blip_does_not_switch() {
+        let start = Instant::now();
+        let mut debouncer = ModeDebouncer::new();
+
+        assert_eq!(debouncer.update(true, at(start, 0), DELAY), None);
+        assert_eq!(debouncer.update(true, at(start, 2), DELAY), None);
+        assert_eq!(debouncer.update(false, at(start, 3), DELAY), None);
+        assert_eq!(debouncer.update(false, at(start, 10), DELAY), None);
+        assert_eq!(debouncer.current(), AudioMode::Listening);
+    }
+
+    #[test]
+    fn sustained_change_switches_after_delay() {
+        let start = Instant::now();
+        let mut debouncer = ModeDebouncer::new();
+
+        assert_eq!(debouncer.update(true, at(start, 0), DELAY), None);
+        assert_eq!(debouncer.update(true, at(start, 3), DELAY), Some(AudioMode::Call));
+        debouncer.commit(AudioMode::Call);
+        assert_eq!(debouncer.current(), AudioMode::Call);
+        assert_eq!(debouncer.update(true, at(start, 4), DELAY), None);
+
+        assert_eq!(debouncer.update(false, at(start, 5), DELAY), None);
+        assert_eq!(debouncer.update(false, at(start, 8), DELAY), Some(AudioMode::Listening));
+    }
+
+    #[test]
+    fn interruption_restarts_the_pending_timer() {
+        let start = Instant::now();
+        let mut debouncer = ModeDebouncer::new();
+
+        debouncer.update(true, at(start, 0), DELAY);
+        debouncer.update(false, at(start, 2), DELAY);
+        // Отсчёт начинается заново с 4-й секунды, а не с нулевой.
+        assert_eq!(debouncer.update(true, at(start, 4), DELAY), None);
+        assert_eq!(debouncer.update(true, at(start, 6), DELAY), None);
+        assert_eq!(debouncer.update(true, at(start, 7), DELAY), Some(AudioMode::Call));
+    }
+
+    #[test]
+    fn uncommitted_switch_is_offered_again() {
+        let start = Instant::now();
+        let mut debouncer = ModeDebouncer::new();
+
+        debouncer.update(true, at(start, 0), DELAY);
+        assert_eq!(debouncer.update(true, at(start, 3), DELAY), Some(AudioMode::Call));
+        assert_eq!(debouncer.update(true, at(start, 4), DELAY), Some(AudioMode::Call));
+        assert_eq!(debouncer.current(), AudioMode::Listening);
+    }
+}
@@ -0,0 +1,36 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Временный каталог теста, удаляемый вместе со всем содержимым.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("btmnr-{}-{}-{}", name, std::process::id(), unique));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Сообщает, что внешний демон для теста не запустился. Обычно тест после
+/// этого пропускается, но если задана переменная `variable` (так делает CI),
+/// отсутствие демона считается ошибкой: иначе целый backend молча
+/// оставался бы непроверенным.
+pub fn daemon_unavailable(daemon: &str, variable: &str) {
+    if std::env::var_os(variable).is_some() {
+        panic!("{} is not available but {} is set", daemon, variable);
+    }
+    eprintln!("{} is not available, skipping (set {} to fail instead)", daemon, variable);
+}